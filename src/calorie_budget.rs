@@ -0,0 +1,95 @@
+use std::io;
+use chrono::{Datelike, NaiveDate, Weekday};
+use serde::{Serialize, Deserialize};
+
+use crate::food_log::{DaySummary, FoodLog, get_calorie_summary};
+use crate::goal::minimum_calories;
+use crate::user_profile::UserProfile;
+
+// Weekly budget mode: the week's calories are the sum of the daily targets,
+// and calories left unused on a day carry forward to the rest of the week
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct WeeklyCalorieBudget {
+    pub week_start: String, // Weekday name, e.g. "Mon"
+    pub bank_cap: f64,      // Most calories that can be carried forward at once
+}
+
+impl Default for WeeklyCalorieBudget {
+    fn default() -> Self {
+        WeeklyCalorieBudget { week_start: "Mon".to_string(), bank_cap: 1000.0 }
+    }
+}
+
+// Where the current week stands against the budget
+#[derive(Debug, Clone)]
+pub struct WeeklyBudgetStatus {
+    pub week_start: String,
+    pub week_end: String,
+    pub budget: f64,           // Sum of the week's daily targets
+    pub banked: f64,           // Carried forward from completed days; negative when over
+    pub remaining: f64,        // Left for today and the rest of the week
+    pub days_left: usize,      // Including today
+    pub suggested_target: f64, // Average daily target for the rest of the week
+    pub below_minimum: bool,   // The suggestion was raised to the safe minimum
+    pub days: Vec<(DaySummary, f64)>, // Each day so far with the bank after it
+}
+
+// Status of the budget week containing `today`. Completed days add what
+// they left unused to the bank, up to the cap, and take any overage out of
+// it; today's intake counts against what remains.
+pub fn weekly_budget_status(
+    food_log: &FoodLog,
+    user_profile: &UserProfile,
+    budget: &WeeklyCalorieBudget,
+    today: &str
+) -> Result<WeeklyBudgetStatus, io::Error> {
+    let today_date = NaiveDate::parse_from_str(today, "%Y-%m-%d").map_err(|_| io::Error::new(
+        io::ErrorKind::InvalidInput,
+        "Invalid date format. Use YYYY-MM-DD."
+    ))?;
+    let week_start_day = budget.week_start.parse::<Weekday>().map_err(|_| io::Error::new(
+        io::ErrorKind::InvalidInput,
+        format!("Unknown day: '{}'", budget.week_start)
+    ))?;
+
+    let days_in = (7 + today_date.weekday().num_days_from_monday() - week_start_day.num_days_from_monday()) % 7;
+    let start = today_date - chrono::Duration::days(days_in as i64);
+    let end = start + chrono::Duration::days(6);
+    let week_start = start.format("%Y-%m-%d").to_string();
+    let week_end = end.format("%Y-%m-%d").to_string();
+
+    let summary = get_calorie_summary(food_log, &week_start, &week_end, user_profile)?;
+    let budget_total = summary.iter().fold(0.0, |total, day| total + day.target);
+
+    let mut banked: f64 = 0.0;
+    let mut days = Vec::new();
+    for day in summary.iter().take(days_in as usize) {
+        // A day with nothing logged was probably not tracked, so it is
+        // taken as on target rather than banked in full
+        if day.intake > 0.0 {
+            banked = (banked - day.difference()).min(budget.bank_cap);
+        }
+        days.push((day.clone(), banked));
+    }
+
+    let rest_of_week = &summary[days_in as usize..];
+    let eaten_today = rest_of_week.first().map_or(0.0, |day| day.net());
+    let targets_left = rest_of_week.iter().fold(0.0, |total, day| total + day.target);
+    let remaining = targets_left + banked - eaten_today;
+
+    let days_left = rest_of_week.len();
+    let suggested = (targets_left + banked) / days_left as f64;
+    let minimum = minimum_calories(&user_profile.gender);
+
+    Ok(WeeklyBudgetStatus {
+        week_start,
+        week_end,
+        budget: budget_total,
+        banked,
+        remaining,
+        days_left,
+        suggested_target: suggested.max(minimum).round(),
+        below_minimum: suggested < minimum,
+        days,
+    })
+}
@@ -0,0 +1,285 @@
+use std::fmt;
+use serde::{Serialize, Serializer, Deserialize, Deserializer};
+
+use crate::user_profile::{ActivityLevel, Gender};
+
+// Everything a strategy may draw on to work out maintenance calories
+pub struct StrategyInput<'a> {
+    pub height: f64, // cm
+    pub weight: f64, // kg
+    pub age: u32,
+    pub gender: &'a Gender,
+    pub activity_level: &'a ActivityLevel,
+    pub body_fat_percentage: Option<f64>,
+    pub adaptive_tdee: Option<f64>,
+    pub fixed_target: Option<f64>,
+}
+
+impl StrategyInput<'_> {
+    // Lean body mass in kg. Without a known body-fat percentage, a rough
+    // average for the gender is assumed.
+    pub fn lean_body_mass(&self) -> f64 {
+        let body_fat_percentage = self.body_fat_percentage.unwrap_or(match self.gender {
+            Gender::Male => 15.0,
+            Gender::Female => 25.0,
+        });
+        self.weight * (100.0 - body_fat_percentage) / 100.0
+    }
+}
+
+pub fn activity_multiplier(activity_level: &ActivityLevel) -> f64 {
+    match activity_level {
+        ActivityLevel::Sedentary => 1.2,
+        ActivityLevel::LightlyActive => 1.375,
+        ActivityLevel::ModeratelyActive => 1.55,
+        ActivityLevel::VeryActive => 1.725,
+        ActivityLevel::SuperActive => 1.9,
+    }
+}
+
+// A way of working out a user's maintenance calories. To add a formula,
+// implement this and list it in `REGISTRY`.
+pub trait CalorieStrategy: Sync {
+    // Stored in users.yaml, so it must never change once released
+    fn name(&self) -> &'static str;
+
+    fn display_name(&self) -> &'static str;
+
+    // Basal metabolic rate, or None if the strategy does not work from one
+    // or lacks the data it needs
+    fn bmr(&self, input: &StrategyInput) -> Option<f64>;
+
+    // Maintenance calories: BMR scaled by activity level unless overridden
+    fn maintenance(&self, input: &StrategyInput) -> Option<f64> {
+        self.bmr(input).map(|bmr| (bmr * activity_multiplier(input.activity_level)).round())
+    }
+
+    // Works from lean body mass, so needs a body-fat percentage to be accurate
+    fn uses_lean_mass(&self) -> bool {
+        false
+    }
+
+    // Whether a weight goal adjusts the result, or it is used as is
+    fn applies_goal(&self) -> bool {
+        true
+    }
+}
+
+struct MifflinStJeor;
+
+impl CalorieStrategy for MifflinStJeor {
+    fn name(&self) -> &'static str { "MifflinStJeor" }
+    fn display_name(&self) -> &'static str { "Mifflin-St Jeor" }
+
+    fn bmr(&self, input: &StrategyInput) -> Option<f64> {
+        let base = 10.0 * input.weight + 6.25 * input.height - 5.0 * input.age as f64;
+        Some(match input.gender {
+            Gender::Male => base + 5.0,
+            Gender::Female => base - 161.0,
+        })
+    }
+}
+
+struct KatchMcArdle;
+
+impl CalorieStrategy for KatchMcArdle {
+    fn name(&self) -> &'static str { "KatchMcArdle" }
+    fn display_name(&self) -> &'static str { "Katch-McArdle" }
+
+    fn bmr(&self, input: &StrategyInput) -> Option<f64> {
+        Some(370.0 + 21.6 * input.lean_body_mass())
+    }
+
+    fn uses_lean_mass(&self) -> bool { true }
+}
+
+struct HarrisBenedict;
+
+impl CalorieStrategy for HarrisBenedict {
+    fn name(&self) -> &'static str { "HarrisBenedict" }
+    fn display_name(&self) -> &'static str { "Harris-Benedict" }
+
+    fn bmr(&self, input: &StrategyInput) -> Option<f64> {
+        let age = input.age as f64;
+        Some(match input.gender {
+            Gender::Male => 66.0 + (13.75 * input.weight) + (5.0 * input.height) - (6.76 * age),
+            Gender::Female => 655.0 + (9.56 * input.weight) + (1.85 * input.height) - (4.68 * age),
+        })
+    }
+}
+
+// Maintenance estimated from the user's own intake and weight trend
+struct Adaptive;
+
+impl CalorieStrategy for Adaptive {
+    fn name(&self) -> &'static str { "Adaptive" }
+    fn display_name(&self) -> &'static str { "Adaptive (estimated from your food log and weight journal)" }
+
+    fn bmr(&self, _input: &StrategyInput) -> Option<f64> {
+        None
+    }
+
+    fn maintenance(&self, input: &StrategyInput) -> Option<f64> {
+        input.adaptive_tdee
+    }
+}
+
+struct Cunningham;
+
+impl CalorieStrategy for Cunningham {
+    fn name(&self) -> &'static str { "Cunningham" }
+    fn display_name(&self) -> &'static str { "Cunningham" }
+
+    fn bmr(&self, input: &StrategyInput) -> Option<f64> {
+        Some(500.0 + 22.0 * input.lean_body_mass())
+    }
+
+    fn uses_lean_mass(&self) -> bool { true }
+}
+
+// WHO/FAO/UNU (1985) equations, from weight by age band
+struct WhoFaoUnu;
+
+impl CalorieStrategy for WhoFaoUnu {
+    fn name(&self) -> &'static str { "WhoFaoUnu" }
+    fn display_name(&self) -> &'static str { "WHO/FAO/UNU" }
+
+    fn bmr(&self, input: &StrategyInput) -> Option<f64> {
+        let (slope, intercept) = match (input.gender, input.age) {
+            (Gender::Male, 0..=2) => (60.9, -54.0),
+            (Gender::Male, 3..=9) => (22.7, 495.0),
+            (Gender::Male, 10..=17) => (17.5, 651.0),
+            (Gender::Male, 18..=29) => (15.3, 679.0),
+            (Gender::Male, 30..=59) => (11.6, 879.0),
+            (Gender::Male, _) => (13.5, 487.0),
+            (Gender::Female, 0..=2) => (61.0, -51.0),
+            (Gender::Female, 3..=9) => (22.5, 499.0),
+            (Gender::Female, 10..=17) => (12.2, 746.0),
+            (Gender::Female, 18..=29) => (14.7, 496.0),
+            (Gender::Female, 30..=59) => (8.7, 829.0),
+            (Gender::Female, _) => (10.5, 596.0),
+        };
+        Some(slope * input.weight + intercept)
+    }
+}
+
+// Schofield (1985) equations, from weight by age band
+struct Schofield;
+
+impl CalorieStrategy for Schofield {
+    fn name(&self) -> &'static str { "Schofield" }
+    fn display_name(&self) -> &'static str { "Schofield" }
+
+    fn bmr(&self, input: &StrategyInput) -> Option<f64> {
+        let (slope, intercept) = match (input.gender, input.age) {
+            (Gender::Male, 0..=2) => (59.512, -30.4),
+            (Gender::Male, 3..=9) => (22.706, 504.3),
+            (Gender::Male, 10..=17) => (17.686, 658.2),
+            (Gender::Male, 18..=29) => (15.057, 692.2),
+            (Gender::Male, 30..=59) => (11.472, 873.1),
+            (Gender::Male, _) => (11.711, 587.7),
+            (Gender::Female, 0..=2) => (58.317, -31.1),
+            (Gender::Female, 3..=9) => (20.315, 485.9),
+            (Gender::Female, 10..=17) => (13.384, 692.6),
+            (Gender::Female, 18..=29) => (14.818, 486.6),
+            (Gender::Female, 30..=59) => (8.126, 845.6),
+            (Gender::Female, _) => (9.082, 658.5),
+        };
+        Some(slope * input.weight + intercept)
+    }
+}
+
+// A daily target the user chose themselves
+struct Fixed;
+
+impl CalorieStrategy for Fixed {
+    fn name(&self) -> &'static str { "Fixed" }
+    fn display_name(&self) -> &'static str { "Fixed (a daily target you choose)" }
+
+    fn bmr(&self, _input: &StrategyInput) -> Option<f64> {
+        None
+    }
+
+    fn maintenance(&self, input: &StrategyInput) -> Option<f64> {
+        input.fixed_target
+    }
+
+    fn applies_goal(&self) -> bool { false }
+}
+
+// Every known strategy, in the order they are offered to the user
+static REGISTRY: [&dyn CalorieStrategy; 8] = [
+    &MifflinStJeor,
+    &KatchMcArdle,
+    &HarrisBenedict,
+    &Adaptive,
+    &Cunningham,
+    &WhoFaoUnu,
+    &Schofield,
+    &Fixed,
+];
+
+// A user's chosen strategy. Serialized by its stable name.
+#[derive(Clone, Copy)]
+pub struct TargetCalorieCalcStrategy(&'static dyn CalorieStrategy);
+
+impl TargetCalorieCalcStrategy {
+    pub const MIFFLIN_ST_JEOR: Self = Self(&MifflinStJeor);
+    pub const ADAPTIVE: Self = Self(&Adaptive);
+    pub const FIXED: Self = Self(&Fixed);
+
+    pub fn all() -> impl Iterator<Item = Self> {
+        REGISTRY.iter().map(|strategy| Self(*strategy))
+    }
+
+    pub fn from_name(name: &str) -> Option<Self> {
+        Self::all().find(|strategy| strategy.name() == name)
+    }
+
+    pub fn name(&self) -> &'static str {
+        self.0.name()
+    }
+
+    pub fn display_name(&self) -> &'static str {
+        self.0.display_name()
+    }
+
+    pub fn maintenance(&self, input: &StrategyInput) -> Option<f64> {
+        self.0.maintenance(input)
+    }
+
+    pub fn uses_lean_mass(&self) -> bool {
+        self.0.uses_lean_mass()
+    }
+
+    pub fn applies_goal(&self) -> bool {
+        self.0.applies_goal()
+    }
+}
+
+impl PartialEq for TargetCalorieCalcStrategy {
+    fn eq(&self, other: &Self) -> bool {
+        self.name() == other.name()
+    }
+}
+
+impl fmt::Debug for TargetCalorieCalcStrategy {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.name())
+    }
+}
+
+impl Serialize for TargetCalorieCalcStrategy {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(self.name())
+    }
+}
+
+impl<'de> Deserialize<'de> for TargetCalorieCalcStrategy {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let name = String::deserialize(deserializer)?;
+        Self::from_name(&name).ok_or_else(|| serde::de::Error::custom(
+            format!("unknown calorie calculation strategy '{}'", name)
+        ))
+    }
+}
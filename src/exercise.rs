@@ -0,0 +1,41 @@
+use serde::{Serialize, Deserialize};
+
+// A bout of exercise logged on a day
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ExerciseEntry {
+    pub id: u64, // Shares the ID sequence of the user's food entries
+    pub activity: String,
+    pub duration_minutes: f64,
+    pub calories_burned: f64,
+    #[serde(default)]
+    pub estimated: bool, // Burn was estimated from a MET value rather than entered
+}
+
+// Metabolic equivalents (MET) of common activities, from the
+// Compendium of Physical Activities
+pub const ACTIVITIES: [(&str, f64); 12] = [
+    ("Walking", 3.5),
+    ("Brisk Walking", 4.3),
+    ("Running", 9.8),
+    ("Cycling", 7.5),
+    ("Swimming", 6.0),
+    ("Strength Training", 5.0),
+    ("HIIT", 8.0),
+    ("Yoga", 2.5),
+    ("Hiking", 6.0),
+    ("Rowing", 7.0),
+    ("Dancing", 5.0),
+    ("Team Sports", 7.0),
+];
+
+// MET value of a known activity, ignoring case
+pub fn met_for(activity: &str) -> Option<f64> {
+    ACTIVITIES.iter()
+        .find(|(name, _)| name.eq_ignore_ascii_case(activity))
+        .map(|(_, met)| *met)
+}
+
+// Calories burned: MET x body weight in kg x hours
+pub fn estimate_calories_burned(met: f64, weight_kg: f64, duration_minutes: f64) -> f64 {
+    met * weight_kg * duration_minutes / 60.0
+}
@@ -1,12 +1,16 @@
 use std::fs;
 use std::io;
-use std::io::Write; // Add this import for flush() method
+use std::ops::{Add, Sub};
 use std::path::Path;
 use serde::{Serialize, Deserialize};
-use reqwest;
 use scraper::{Html, Selector};
 use serde_json::json;
 
+use crate::input;
+use crate::schema::{self, SchemaKind};
+use crate::user_profile::{DietaryRestrictions, UserProfile};
+use crate::food_log::{FoodLog, LogEntry};
+
 #[derive(Debug)]
 pub struct FoodDatabase {
     pub basic_foods: Vec<BasicFood>,
@@ -29,19 +33,12 @@ impl FoodDatabase {
 
     pub fn load(&mut self) -> Result<(), io::Error> {
         // Load basic foods
-        if Path::new(&self.basic_foods_path).exists() {
-            let contents = fs::read_to_string(&self.basic_foods_path)?;
-            let db: BasicFoodsWrapper = serde_yaml::from_str(&contents)
-                .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))?;
+        if let Some(db) = schema::load_versioned::<BasicFoodsWrapper>(&self.basic_foods_path, SchemaKind::BasicFoods)? {
             self.basic_foods = db.basic_foods;
         }
 
         // Then load composite foods
-        if Path::new(&self.composite_foods_path).exists() {
-            let contents = fs::read_to_string(&self.composite_foods_path)?;
-            let db: SerializedCompositeFoodsWrapper = serde_yaml::from_str(&contents)
-                .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))?;
-            
+        if let Some(db) = schema::load_versioned::<SerializedCompositeFoodsWrapper>(&self.composite_foods_path, SchemaKind::CompositeFoods)? {
             self.composite_foods = Vec::new();
             
             // Process each composite food reference and resolve its components
@@ -63,6 +60,9 @@ impl FoodDatabase {
                     identifier: serialized_food.identifier,
                     keywords: serialized_food.keywords,
                     components,
+                    built_from: serialized_food.built_from.into_iter()
+                        .map(|component| (component.food_id, component.quantity))
+                        .collect(),
                 });
             }
         }
@@ -80,8 +80,7 @@ impl FoodDatabase {
         let basic_db = BasicFoodsWrapper {
             basic_foods: self.basic_foods.clone(),
         };
-        let yaml = serde_yaml::to_string(&basic_db)
-            .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))?;
+        let yaml = schema::to_versioned_yaml(&basic_db, SchemaKind::BasicFoods)?;
         fs::write(&self.basic_foods_path, yaml)?;
 
         // Convert composite foods to serializable format and save
@@ -93,8 +92,7 @@ impl FoodDatabase {
             composite_foods: serialized_foods,
         };
         
-        let yaml = serde_yaml::to_string(&composite_db)
-            .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))?;
+        let yaml = schema::to_versioned_yaml(&composite_db, SchemaKind::CompositeFoods)?;
         fs::write(&self.composite_foods_path, yaml)?;
 
         Ok(())
@@ -123,22 +121,28 @@ impl FoodDatabase {
         results
     }
 
-    pub fn add_basic_food(&mut self, identifier: &str, keywords: Vec<String>, calories_per_serving: f64) -> Result<(), io::Error> {
+    // Search foods, leaving out anything that conflicts with the given restrictions.
+    // Also returns how many matches were hidden.
+    pub fn search_foods_with_restrictions(&self, prefix: &str, restrictions: &DietaryRestrictions) -> (Vec<(&str, f64)>, usize) {
+        let (allowed, hidden): (Vec<_>, Vec<_>) = self.search_foods(prefix)
+            .into_iter()
+            .partition(|(identifier, _)| {
+                self.get_dietary_info(identifier)
+                    .is_none_or(|info| restrictions.violations(&info).is_empty())
+            });
+
+        (allowed, hidden.len())
+    }
+
+    pub fn add_basic_food(&mut self, basic_food: BasicFood) -> Result<(), io::Error> {
         // Check if a food with this identifier already exists
-        if self.basic_foods.iter().any(|food| food.identifier == identifier) {
+        if self.basic_foods.iter().any(|food| food.identifier == basic_food.identifier) {
             return Err(io::Error::new(
                 io::ErrorKind::AlreadyExists, 
-                format!("Basic food '{}' already exists", identifier)
+                format!("Basic food '{}' already exists", basic_food.identifier)
             ));
         }
         
-        // Create new basic food
-        let basic_food = BasicFood {
-            identifier: identifier.to_string(),
-            keywords,
-            calories_per_serving,
-        };
-        
         // Add to vector
         self.basic_foods.push(basic_food);
         
@@ -159,6 +163,7 @@ impl FoodDatabase {
         
         // Build components from IDs
         let mut components = Vec::new();
+        let mut built_from = Vec::new();
         
         for (food_id, quantity) in component_ids {
             // Find the basic food with the matching identifier
@@ -174,6 +179,8 @@ impl FoodDatabase {
                     let adjusted_quantity = comp_quantity * quantity;
                     components.push((basic_food.clone(), adjusted_quantity));
                 }
+                // and remember where they came from
+                built_from.push((food_id, quantity));
             } else {
                 return Err(io::Error::new(
                     io::ErrorKind::NotFound, 
@@ -187,6 +194,7 @@ impl FoodDatabase {
             identifier: identifier.to_string(),
             keywords,
             components,
+            built_from,
         };
         
         // Add to vector
@@ -208,6 +216,88 @@ impl FoodDatabase {
         self.composite_foods.iter().find(|f| f.identifier == identifier)
     }
 
+    // Dietary attributes of a basic or composite food
+    pub fn get_dietary_info(&self, identifier: &str) -> Option<DietaryInfo> {
+        if let Some(food) = self.get_basic_food(identifier) {
+            Some(food.dietary.clone())
+        } else {
+            self.get_composite_food(identifier).map(|food| food.get_dietary_info())
+        }
+    }
+
+    // Find everything that depends on a food: composites that contain it,
+    // directly or through other composites, and every user's log entries
+    // that reference it. Logs are only read, never loaded for editing.
+    pub fn find_usages(&self, identifier: &str, users: &[UserProfile]) -> Result<FoodUsage, io::Error> {
+        let composites = self.composite_foods.iter()
+            .filter(|composite| composite.identifier != identifier)
+            .map(|composite| (composite.identifier.clone(), self.quantity_in_composite(identifier, &composite.identifier)))
+            .filter(|(_, quantity)| *quantity > 0.0)
+            .collect();
+
+        let mut log_entries = Vec::new();
+        for user in users {
+            let food_log = FoodLog::read_only(&user.id)?;
+
+            let mut dates = food_log.get_logged_dates();
+            dates.sort();
+            for date in dates {
+                let servings = food_log.get_entries_for_date(&date).into_iter().flatten()
+                    .fold(0.0, |total, entry| total + self.servings_in_entry(identifier, entry));
+                if servings > 0.0 {
+                    log_entries.push((user.name.clone(), date, servings));
+                }
+            }
+        }
+
+        Ok(FoodUsage { composites, log_entries })
+    }
+
+    // Servings of `identifier` in one serving of a composite. Basic foods are
+    // counted from the flattened components, composites through the ones each
+    // composite was built from. A composite can only be built from composites
+    // that already exist, so this always terminates.
+    fn quantity_in_composite(&self, identifier: &str, composite_id: &str) -> f64 {
+        let Some(composite) = self.get_composite_food(composite_id) else {
+            return 0.0;
+        };
+        if self.get_composite_food(identifier).is_none() {
+            return composite.components.iter()
+                .filter(|(food, _)| food.identifier == identifier)
+                .fold(0.0, |total, (_, quantity)| total + quantity);
+        }
+        composite.built_from.iter().fold(0.0, |total, (source, quantity)| {
+            let per_source = if source == identifier { 1.0 } else { self.quantity_in_composite(identifier, source) };
+            total + quantity * per_source
+        })
+    }
+
+    // Servings of `identifier` in a log entry, whether it was logged on its
+    // own, inside a composite entry, or inside a composite built from it
+    fn servings_in_entry(&self, identifier: &str, entry: &LogEntry) -> f64 {
+        if entry.food_id == identifier {
+            entry.servings
+        } else if !entry.is_composite() {
+            0.0
+        } else if self.get_composite_food(identifier).is_some() {
+            entry.servings * self.quantity_in_composite(identifier, &entry.food_id)
+        } else {
+            // Basic foods are counted from the recipe the entry was logged with
+            entry.expand().iter()
+                .filter(|component| component.food_id == identifier)
+                .fold(0.0, |total, component| total + component.servings)
+        }
+    }
+
+    // Cost of one serving of a basic or composite food, if it is known
+    pub fn get_cost_per_serving(&self, identifier: &str) -> Option<(f64, String)> {
+        if let Some(food) = self.get_basic_food(identifier) {
+            food.price.as_ref().map(|p| (p.amount, p.currency.clone()))
+        } else {
+            self.get_composite_food(identifier).and_then(|food| food.get_cost())
+        }
+    }
+
     // Enhanced website scraping method
     pub async fn scrape_website(&self, url: &str) -> Result<String, reqwest::Error> {
         println!("Sending request to URL: {}", url);
@@ -237,10 +327,9 @@ impl FoodDatabase {
         }
         
         // Extract meta description if available
-        if let Some(meta_desc) = document.select(&Selector::parse("meta[name='description']").unwrap_or_else(|_| Selector::parse("meta").unwrap())).next() {
-            if let Some(content) = meta_desc.value().attr("content") {
-                text_content.push_str(&format!("Page Description: {}\n\n", content));
-            }
+        if let Some(meta_desc) = document.select(&Selector::parse("meta[name='description']").unwrap_or_else(|_| Selector::parse("meta").unwrap())).next()
+            && let Some(content) = meta_desc.value().attr("content") {
+            text_content.push_str(&format!("Page Description: {}\n\n", content));
         }
 
         let p_selector = Selector::parse("p").unwrap();
@@ -267,7 +356,7 @@ impl FoodDatabase {
         for list_item in document.select(&li_selector) {
             text_content.push_str("• ");
             text_content.push_str(&list_item.text().collect::<Vec<_>>().join(" "));
-            text_content.push_str("\n");
+            text_content.push('\n');
         }
         
         // Extract divs if not enough content
@@ -286,12 +375,11 @@ impl FoodDatabase {
         }
         
         // Extract from <main> tag if available
-        if let Ok(main_selector) = Selector::parse("main") {
-            if let Some(main_elem) = document.select(&main_selector).next() {
-                text_content.push_str("Main Content:\n");
-                text_content.push_str(&main_elem.text().collect::<Vec<_>>().join(" "));
-                text_content.push_str("\n\n");
-            }
+        if let Ok(main_selector) = Selector::parse("main")
+            && let Some(main_elem) = document.select(&main_selector).next() {
+            text_content.push_str("Main Content:\n");
+            text_content.push_str(&main_elem.text().collect::<Vec<_>>().join(" "));
+            text_content.push_str("\n\n");
         }
         
         // Extract from <article> tag if available
@@ -339,7 +427,7 @@ impl FoodDatabase {
                 
                 content
             },
-            Err(e) => return Err(io::Error::new(io::ErrorKind::Other, format!("Failed to scrape website: {}", e))),
+            Err(e) => return Err(io::Error::other(format!("Failed to scrape website: {}", e))),
         };
         
         println!("Generating food data using Ollama LLM...");
@@ -347,7 +435,7 @@ impl FoodDatabase {
         // Then use the LLM to generate food data
         let food_data = match self.generate_food_data_with_ollama(&website_content).await {
             Ok(data) => data,
-            Err(e) => return Err(io::Error::new(io::ErrorKind::Other, format!("Failed to generate food data: {}", e))),
+            Err(e) => return Err(io::Error::other(format!("Failed to generate food data: {}", e))),
         };
         
         Ok(food_data)
@@ -426,6 +514,8 @@ impl FoodDatabase {
         let mut identifier = String::new();
         let mut keywords = Vec::new();
         let mut calories = 0.0;
+        let num_regex = regex::Regex::new(r"(\d+(?:\.\d+)?)")
+            .unwrap_or_else(|_| regex::Regex::new(r"\d+").unwrap());
 
         // Parse the response line by line
         for line in llm_response.lines() {
@@ -433,15 +523,14 @@ impl FoodDatabase {
             
             // Extract identifier
             if line.to_lowercase().starts_with("identifier:") {
-                identifier = line.splitn(2, ':').nth(1)
-                    .unwrap_or("").trim()
+                identifier = line.split_once(':').map_or("", |(_, v)| v).trim()
                     .replace(" ", "_")
                     .to_lowercase();
             }
             
             // Extract keywords with better handling
             if line.to_lowercase().starts_with("keywords:") {
-                let kw_part = line.splitn(2, ':').nth(1).unwrap_or("").trim();
+                let kw_part = line.split_once(':').map_or("", |(_, v)| v).trim();
                 // Handle both comma-separated and bracket formats
                 let clean_kw = kw_part
                     .trim_start_matches('[')
@@ -456,22 +545,17 @@ impl FoodDatabase {
             // Extract calories with better number parsing
             if line.to_lowercase().starts_with("calories_per_serving:") {
                 // Try different number formatting options
-                let num_part = line.splitn(2, ':').nth(1).unwrap_or("").trim();
+                let num_part = line.split_once(':').map_or("", |(_, v)| v).trim();
                 
                 // First try a direct parse
                 if let Ok(val) = num_part.parse::<f64>() {
                     calories = val;
                 } else {
                     // Try extracting just the first number in the string
-                    let num_regex = regex::Regex::new(r"(\d+(?:\.\d+)?)")
-                        .unwrap_or_else(|_| regex::Regex::new(r"\d+").unwrap());
-                    
-                    if let Some(caps) = num_regex.captures(num_part) {
-                        if let Some(m) = caps.get(1) {
-                            if let Ok(val) = m.as_str().parse::<f64>() {
-                                calories = val;
-                            }
-                        }
+                    if let Some(caps) = num_regex.captures(num_part)
+                        && let Some(m) = caps.get(1)
+                        && let Ok(val) = m.as_str().parse::<f64>() {
+                        calories = val;
                     }
                 }
             }
@@ -524,7 +608,7 @@ impl FoodDatabase {
             
             // Sort by count and take top 5
             let mut word_vec: Vec<_> = word_counts.into_iter().collect();
-            word_vec.sort_by(|a, b| b.1.cmp(&a.1));
+            word_vec.sort_by_key(|b| std::cmp::Reverse(b.1));
             
             keywords = word_vec.into_iter()
                 .take(5)
@@ -535,17 +619,12 @@ impl FoodDatabase {
         // 3. Handle missing calories
         if calories == 0.0 {
             // Try to find any number between 50-800 (reasonable calorie range)
-            let num_regex = regex::Regex::new(r"(\d+(?:\.\d+)?)")
-                .unwrap_or_else(|_| regex::Regex::new(r"\d+").unwrap());
-            
             for cap in num_regex.captures_iter(website_content) {
-                if let Some(m) = cap.get(1) {
-                    if let Ok(val) = m.as_str().parse::<f64>() {
-                        if val >= 50.0 && val <= 800.0 {
-                            calories = val;
-                            break;
-                        }
-                    }
+                if let Some(m) = cap.get(1)
+                    && let Ok(val) = m.as_str().parse::<f64>()
+                    && (50.0..=800.0).contains(&val) {
+                    calories = val;
+                    break;
                 }
             }
             
@@ -575,6 +654,11 @@ impl FoodDatabase {
             identifier,
             keywords,
             calories_per_serving: calories,
+            dietary: DietaryInfo::default(),
+            price: None,
+            glycemic_index: None,
+            available_carbs: None,
+            macros: None,
         })
     }
     
@@ -586,18 +670,10 @@ impl FoodDatabase {
         
         if self.basic_foods.iter().any(|food| food.identifier == food_data.identifier) {
             println!("Warning: A food with identifier '{}' already exists", food_data.identifier);
-            print!("Would you like to use a different identifier? (y/n): ");
-            io::stdout().flush()?;
-            
-            let mut input = String::new();
-            io::stdin().read_line(&mut input)?;
+            let input = input::read_line("Would you like to use a different identifier? (y/n):")?;
             
             if input.trim().to_lowercase() == "y" {
-                print!("Enter new identifier: ");
-                io::stdout().flush()?;
-                
-                let mut new_id = String::new();
-                io::stdin().read_line(&mut new_id)?;
+                let new_id = input::read_line("Enter new identifier:")?;
                 food_data.identifier = new_id.trim().to_string();
             } else {
                 return Err(io::Error::new(
@@ -614,11 +690,7 @@ impl FoodDatabase {
         println!("  3. Calories per serving: {}", food_data.calories_per_serving);
         
         // Ask if the user wants to edit the data
-        print!("\nWould you like to edit this information? (y/n): ");
-        io::stdout().flush()?;
-        
-        let mut input = String::new();
-        io::stdin().read_line(&mut input)?;
+        let input = input::read_line("Would you like to edit this information? (y/n):")?;
         
         if input.trim().to_lowercase() == "y" {
             // Edit mode
@@ -629,27 +701,15 @@ impl FoodDatabase {
                 println!("  3. Calories per serving: {}", food_data.calories_per_serving);
                 println!("  4. Done editing");
                 
-                print!("\nSelect an option to edit (1-4): ");
-                io::stdout().flush()?;
-                
-                let mut choice = String::new();
-                io::stdin().read_line(&mut choice)?;
+                let choice = input::read_line("Select an option to edit (1-4):")?;
                 
                 match choice.trim() {
                     "1" => {
-                        print!("Enter new identifier: ");
-                        io::stdout().flush()?;
-                        
-                        let mut new_id = String::new();
-                        io::stdin().read_line(&mut new_id)?;
+                        let new_id = input::read_line("Enter new identifier:")?;
                         food_data.identifier = new_id.trim().to_string();
                     },
                     "2" => {
-                        print!("Enter new keywords (comma-separated): ");
-                        io::stdout().flush()?;
-                        
-                        let mut new_keywords = String::new();
-                        io::stdin().read_line(&mut new_keywords)?;
+                        let new_keywords = input::read_line("Enter new keywords (comma-separated):")?;
                         
                         food_data.keywords = new_keywords.trim()
                             .split(',')
@@ -658,11 +718,7 @@ impl FoodDatabase {
                             .collect();
                     },
                     "3" => {
-                        print!("Enter new calories per serving: ");
-                        io::stdout().flush()?;
-                        
-                        let mut new_calories = String::new();
-                        io::stdin().read_line(&mut new_calories)?;
+                        let new_calories = input::read_line("Enter new calories per serving:")?;
                         
                         if let Ok(cal) = new_calories.trim().parse::<f64>() {
                             food_data.calories_per_serving = cal;
@@ -677,11 +733,7 @@ impl FoodDatabase {
         }
         
         // Ask for final confirmation
-        print!("\nWould you like to add this food to the database? (y/n): ");
-        io::stdout().flush()?;
-        
-        let mut input = String::new();
-        io::stdin().read_line(&mut input)?;
+        let input = input::read_line("Would you like to add this food to the database? (y/n):")?;
         
         if input.trim().to_lowercase() == "y" {
             // Add to vector
@@ -705,12 +757,256 @@ pub struct BasicFood {
     pub identifier: String,
     pub keywords: Vec<String>,
     pub calories_per_serving: f64,
+    #[serde(default)]
+    pub dietary: DietaryInfo,
+    #[serde(default)]
+    pub price: Option<Price>,
+    #[serde(default)]
+    pub glycemic_index: Option<f64>,
+    #[serde(default)]
+    pub available_carbs: Option<f64>, // grams of available carbohydrate per serving
+    #[serde(default)]
+    pub macros: Option<Macros>, // per serving
+}
+
+// Where a food is used, as returned by `FoodDatabase::find_usages`
+#[derive(Debug)]
+pub struct FoodUsage {
+    pub composites: Vec<(String, f64)>,          // (composite identifier, servings of the food per composite serving)
+    pub log_entries: Vec<(String, String, f64)>, // (user name, date, servings logged)
+}
+
+impl FoodUsage {
+    pub fn is_empty(&self) -> bool {
+        self.composites.is_empty() && self.log_entries.is_empty()
+    }
+}
+
+// Price of a single serving as of a given date
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Price {
+    pub amount: f64,
+    pub currency: String,
+    pub date: String, // YYYY-MM-DD
+}
+
+// Protein, carbohydrate and fat in grams
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize, Deserialize)]
+pub struct Macros {
+    pub protein: f64,
+    pub carbs: f64,
+    pub fat: f64,
+}
+
+impl Macros {
+    pub fn scaled(&self, factor: f64) -> Macros {
+        Macros {
+            protein: self.protein * factor,
+            carbs: self.carbs * factor,
+            fat: self.fat * factor,
+        }
+    }
+
+    // Energy from the macros: 4 kcal/g for protein and carbs, 9 for fat
+    pub fn calories(&self) -> f64 {
+        4.0 * self.protein + 4.0 * self.carbs + 9.0 * self.fat
+    }
+
+    pub fn describe(&self) -> String {
+        format!("protein {:.0} g, carbs {:.0} g, fat {:.0} g", self.protein, self.carbs, self.fat)
+    }
+}
+
+impl Add for Macros {
+    type Output = Macros;
+
+    fn add(self, other: Macros) -> Macros {
+        Macros {
+            protein: self.protein + other.protein,
+            carbs: self.carbs + other.carbs,
+            fat: self.fat + other.fat,
+        }
+    }
+}
+
+impl Sub for Macros {
+    type Output = Macros;
+
+    fn sub(self, other: Macros) -> Macros {
+        Macros {
+            protein: self.protein - other.protein,
+            carbs: self.carbs - other.carbs,
+            fat: self.fat - other.fat,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum Allergen {
+    Nuts,
+    Peanuts,
+    Gluten,
+    Dairy,
+    Eggs,
+    Soy,
+    Fish,
+    Shellfish,
+    Sesame,
+}
+
+impl Allergen {
+    pub const ALL: [Allergen; 9] = [
+        Allergen::Nuts,
+        Allergen::Peanuts,
+        Allergen::Gluten,
+        Allergen::Dairy,
+        Allergen::Eggs,
+        Allergen::Soy,
+        Allergen::Fish,
+        Allergen::Shellfish,
+        Allergen::Sesame,
+    ];
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            Allergen::Nuts => "nuts",
+            Allergen::Peanuts => "peanuts",
+            Allergen::Gluten => "gluten",
+            Allergen::Dairy => "dairy",
+            Allergen::Eggs => "eggs",
+            Allergen::Soy => "soy",
+            Allergen::Fish => "fish",
+            Allergen::Shellfish => "shellfish",
+            Allergen::Sesame => "sesame",
+        }
+    }
+
+    pub fn from_name(name: &str) -> Option<Allergen> {
+        let name = name.trim().to_lowercase();
+        Allergen::ALL.iter().copied().find(|a| a.name() == name)
+    }
+
+    // Parse a comma-separated list, returning the allergens and any unrecognised names
+    pub fn parse_list(input: &str) -> (Vec<Allergen>, Vec<String>) {
+        let mut allergens = Vec::new();
+        let mut unknown = Vec::new();
+
+        for name in input.split(',').map(str::trim).filter(|n| !n.is_empty()) {
+            match Allergen::from_name(name) {
+                Some(allergen) => {
+                    if !allergens.contains(&allergen) {
+                        allergens.push(allergen);
+                    }
+                }
+                None => unknown.push(name.to_string()),
+            }
+        }
+
+        (allergens, unknown)
+    }
+}
+
+// Dietary attributes of a food. A flag of None means it was never recorded,
+// which is the case for every food added before these existed.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct DietaryInfo {
+    #[serde(default)]
+    pub allergens: Vec<Allergen>, // Allergens the food is known to contain
+    #[serde(default)]
+    pub vegan: Option<bool>,
+    #[serde(default)]
+    pub vegetarian: Option<bool>,
+    #[serde(default)]
+    pub halal: Option<bool>,
+    // Only ever Some(true) or None: a food with gluten lists the allergen
+    // instead. Read it through `is_gluten_free`.
+    #[serde(default)]
+    pub gluten_free: Option<bool>,
+}
+
+impl DietaryInfo {
+    // Containing gluten always wins over a recorded gluten-free flag
+    pub fn is_gluten_free(&self) -> Option<bool> {
+        if self.allergens.contains(&Allergen::Gluten) {
+            Some(false)
+        } else {
+            self.gluten_free
+        }
+    }
+
+    // Combine the attributes of several ingredients: allergens are merged,
+    // while a flag only holds if it holds for every ingredient, and is
+    // unknown if it is unknown for any that does not rule it out
+    pub fn combine<'a>(infos: impl IntoIterator<Item = &'a DietaryInfo>) -> DietaryInfo {
+        let mut combined = DietaryInfo {
+            allergens: Vec::new(),
+            vegan: Some(true),
+            vegetarian: Some(true),
+            halal: Some(true),
+            gluten_free: Some(true),
+        };
+        let mut any = false;
+
+        for info in infos {
+            any = true;
+            for allergen in &info.allergens {
+                if !combined.allergens.contains(allergen) {
+                    combined.allergens.push(*allergen);
+                }
+            }
+            combined.vegan = combine_flag(combined.vegan, info.vegan);
+            combined.vegetarian = combine_flag(combined.vegetarian, info.vegetarian);
+            combined.halal = combine_flag(combined.halal, info.halal);
+            combined.gluten_free = combine_flag(combined.gluten_free, info.is_gluten_free());
+        }
+        if combined.gluten_free == Some(false) {
+            combined.gluten_free = None;
+        }
+
+        if any { combined } else { DietaryInfo::default() }
+    }
+
+    // Short human-readable description, e.g. "vegan, gluten-free; contains: soy"
+    pub fn describe(&self) -> String {
+        let mut flags = Vec::new();
+        if self.vegan == Some(true) { flags.push("vegan"); }
+        if self.vegetarian == Some(true) { flags.push("vegetarian"); }
+        if self.halal == Some(true) { flags.push("halal"); }
+        if self.is_gluten_free() == Some(true) { flags.push("gluten-free"); }
+
+        let mut description = flags.join(", ");
+        if !self.allergens.is_empty() {
+            if !description.is_empty() {
+                description.push_str("; ");
+            }
+            let names: Vec<&str> = self.allergens.iter().map(|a| a.name()).collect();
+            description.push_str(&format!("contains: {}", names.join(", ")));
+        }
+        description
+    }
+}
+
+// A known "no" beats an unknown, which beats a known "yes"
+fn combine_flag(a: Option<bool>, b: Option<bool>) -> Option<bool> {
+    match (a, b) {
+        (Some(false), _) | (_, Some(false)) => Some(false),
+        (Some(true), Some(true)) => Some(true),
+        _ => None,
+    }
 }
 
 impl BasicFood {
     pub fn get_calories(&self) -> f64 {
         self.calories_per_serving
     }
+
+    // Glycemic load of one serving: GI x available carbs / 100
+    pub fn get_glycemic_load(&self) -> Option<f64> {
+        match (self.glycemic_index, self.available_carbs) {
+            (Some(gi), Some(carbs)) => Some(gi * carbs / 100.0),
+            _ => None,
+        }
+    }
 }
 
 #[derive(Debug, Clone)]
@@ -718,6 +1014,10 @@ pub struct CompositeFood {
     pub identifier: String,
     pub keywords: Vec<String>,
     pub components: Vec<(BasicFood, f64)>, // (BasicFood, quantity)
+    // Composites this one was built from, with their quantities. Their basic
+    // foods are already flattened into `components`; this only records where
+    // they came from. Empty for composites created before it was kept.
+    pub built_from: Vec<(String, f64)>,
 }
 
 impl CompositeFood {
@@ -727,6 +1027,43 @@ impl CompositeFood {
             .map(|(food, qty)| food.get_calories() * qty)
             .sum()
     }
+
+    // Cost of one serving, only known if every component is priced in the same currency
+    pub fn get_cost(&self) -> Option<(f64, String)> {
+        let mut total = 0.0;
+        let mut currency: Option<&str> = None;
+
+        for (food, qty) in &self.components {
+            let price = food.price.as_ref()?;
+            match currency {
+                Some(c) if c != price.currency => return None,
+                _ => currency = Some(&price.currency),
+            }
+            total += price.amount * qty;
+        }
+
+        currency.map(|c| (total, c.to_string()))
+    }
+
+    // Glycemic load of one serving, only known if every component has GI data
+    pub fn get_glycemic_load(&self) -> Option<f64> {
+        self.components
+            .iter()
+            .map(|(food, qty)| food.get_glycemic_load().map(|gl| gl * qty))
+            .sum()
+    }
+
+    // Macros of one serving, only known if every component has macro data
+    pub fn get_macros(&self) -> Option<Macros> {
+        self.components
+            .iter()
+            .try_fold(Macros::default(), |total, (food, qty)| Some(total + food.macros?.scaled(*qty)))
+    }
+
+    // Derived from the components, so it stays in sync when they change
+    pub fn get_dietary_info(&self) -> DietaryInfo {
+        DietaryInfo::combine(self.components.iter().map(|(food, _)| &food.dietary))
+    }
     
     // Convert to a serializable format
     fn to_serialized(&self) -> SerializedCompositeFood {
@@ -739,6 +1076,12 @@ impl CompositeFood {
                     quantity: *qty,
                 }
             }).collect(),
+            built_from: self.built_from.iter().map(|(food_id, quantity)| {
+                FoodComponent {
+                    food_id: food_id.clone(),
+                    quantity: *quantity,
+                }
+            }).collect(),
         }
     }
 }
@@ -761,9 +1104,89 @@ struct SerializedCompositeFood {
     identifier: String,
     keywords: Vec<String>,
     components: Vec<FoodComponent>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    built_from: Vec<FoodComponent>,
 }
 
 #[derive(Serialize, Deserialize)]
 struct SerializedCompositeFoodsWrapper {
     composite_foods: Vec<SerializedCompositeFood>,
-}
\ No newline at end of file
+}
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // A database that saves into its own temporary directory
+    fn test_db(name: &str) -> FoodDatabase {
+        let dir = std::env::temp_dir().join(format!("yada_db_{}_{}", name, std::process::id()));
+        let mut db = FoodDatabase::new();
+        db.basic_foods_path = dir.join("basic_foods.yaml").to_string_lossy().to_string();
+        db.composite_foods_path = dir.join("composite_foods.yaml").to_string_lossy().to_string();
+        for (identifier, calories) in [("Apple", 95.0), ("Banana", 105.0)] {
+            db.basic_foods.push(BasicFood {
+                identifier: identifier.to_string(),
+                keywords: Vec::new(),
+                calories_per_serving: calories,
+                dietary: DietaryInfo::default(),
+                price: None,
+                glycemic_index: None,
+                available_carbs: None,
+                macros: None,
+            });
+        }
+        db
+    }
+
+    fn cleanup(db: &FoodDatabase) {
+        let _ = fs::remove_dir_all(Path::new(&db.basic_foods_path).parent().unwrap());
+    }
+
+    // Fruit Salad: 2 Apple + 1 Banana
+    // Fruit Bowl: 1.5 Fruit Salad + 1 Apple
+    // Party Platter: 2 Fruit Bowl
+    fn nested_db(name: &str) -> FoodDatabase {
+        let mut db = test_db(name);
+        db.add_composite_food("Fruit Salad", Vec::new(),
+            vec![("Apple".to_string(), 2.0), ("Banana".to_string(), 1.0)]).unwrap();
+        db.add_composite_food("Fruit Bowl", Vec::new(),
+            vec![("Fruit Salad".to_string(), 1.5), ("Apple".to_string(), 1.0)]).unwrap();
+        db.add_composite_food("Party Platter", Vec::new(),
+            vec![("Fruit Bowl".to_string(), 2.0)]).unwrap();
+        db
+    }
+
+    #[test]
+    fn basic_food_usage_counts_flattened_quantities() {
+        let db = nested_db("basic_usage");
+        let usage = db.find_usages("Apple", &[]).unwrap();
+        assert_eq!(usage.composites, vec![
+            ("Fruit Salad".to_string(), 2.0),
+            ("Fruit Bowl".to_string(), 4.0),
+            ("Party Platter".to_string(), 8.0),
+        ]);
+        cleanup(&db);
+    }
+
+    #[test]
+    fn composite_usage_is_transitive() {
+        let db = nested_db("composite_usage");
+        let usage = db.find_usages("Fruit Salad", &[]).unwrap();
+        assert_eq!(usage.composites, vec![
+            ("Fruit Bowl".to_string(), 1.5),
+            ("Party Platter".to_string(), 3.0),
+        ]);
+        assert!(db.find_usages("Party Platter", &[]).unwrap().is_empty());
+        cleanup(&db);
+    }
+
+    #[test]
+    fn composite_sources_survive_reload() {
+        let db = nested_db("reload");
+        let mut reloaded = test_db("reload");
+        reloaded.load().unwrap();
+        let bowl = reloaded.get_composite_food("Fruit Bowl").unwrap();
+        assert_eq!(bowl.built_from, vec![("Fruit Salad".to_string(), 1.5)]);
+        assert_eq!(reloaded.find_usages("Fruit Salad", &[]).unwrap().composites.len(), 2);
+        cleanup(&db);
+    }
+}
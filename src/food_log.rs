@@ -2,19 +2,103 @@ use std::fs;
 use std::io;
 use std::path::Path;
 use std::collections::HashMap;
-use chrono::{Local, NaiveDate};
+use chrono::{Datelike, Local, NaiveDate, Weekday};
 use serde::{Serialize, Deserialize};
 
-use crate::food_database::{FoodDatabase, BasicFood};
+use crate::food_database::{FoodDatabase, BasicFood, CompositeFood, Macros, Price};
 use crate::user_profile::UserProfile;
+use crate::schema::{self, SchemaKind};
+use crate::exercise::ExerciseEntry;
 
 // Struct to handle food logging for a specific user
 #[derive(Debug)]
 pub struct FoodLog {
-    user_name: String, // Used for file naming
+    user_id: String, // Used for file naming
     daily_logs: HashMap<String, DailyLog>,
     pub current_date: String, // Make this public so we can access it from main
     log_dir_path: String,
+    next_entry_id: u64, // Entry IDs are unique across all of a user's dates
+    history: Vec<HistoryRecord>, // Oldest first, bounded by MAX_HISTORY
+    redo_stack: Vec<HistoryRecord>, // Most recently undone last
+    pending: Option<Vec<EntryChange>>, // Changes of the batch in progress, if any
+    templates: Vec<MealTemplate>,
+    recurrence_rules: Vec<RecurrenceRule>,
+}
+
+// Number of actions kept in a user's undo history
+const MAX_HISTORY: usize = 100;
+
+pub const LOG_DIR: &str = "data/logs";
+
+fn log_file_path(log_dir: &str, user_id: &str) -> String {
+    format!("{}/{}_logs.yaml", log_dir, user_id)
+}
+
+// Logs used to be named after the user. Move such a file to the user's ID so
+// it survives renames. Returns true if a file was moved. The legacy path is
+// built exactly as the old code built it, so a name containing '/' finds the
+// file in the subfolder it was written to.
+pub fn adopt_name_based_log(log_dir: &str, user_name: &str, user_id: &str) -> Result<bool, io::Error> {
+    let legacy_path = log_file_path(log_dir, user_name);
+    let path = log_file_path(log_dir, user_id);
+    if !Path::new(&legacy_path).is_file() || Path::new(&path).exists() {
+        return Ok(false);
+    }
+    fs::rename(&legacy_path, &path)?;
+    Ok(true)
+}
+
+// Whether a log file exists for the user ID, e.g. one left by a past user
+pub fn log_exists(user_id: &str) -> bool {
+    Path::new(&log_file_path(LOG_DIR, user_id)).exists()
+}
+
+// Move a deleted user's log into the archive folder. Returns the archived
+// path, or None if the user had no log.
+pub fn archive_log(user_id: &str) -> Result<Option<String>, io::Error> {
+    let path = log_file_path(LOG_DIR, user_id);
+    if !Path::new(&path).exists() {
+        return Ok(None);
+    }
+
+    let archive_dir = format!("{}/archive", LOG_DIR);
+    fs::create_dir_all(&archive_dir)?;
+    let stamp = Local::now().format("%Y%m%d%H%M%S");
+    let archive_path = format!("{}/{}_{}_logs.yaml", archive_dir, user_id, stamp);
+    fs::rename(&path, &archive_path)?;
+    Ok(Some(archive_path))
+}
+
+// Replace the item with the change's ID in `items`, which must currently
+// match `from`. `None` means the item is absent before or after.
+fn replace_item<T: Clone + PartialEq>(items: &mut Vec<T>, id: fn(&T) -> u64, change: &EntryChange, from: Option<&T>, to: Option<&T>) -> Result<(), io::Error> {
+    let current = items.iter().position(|item| id(item) == change.entry_id);
+    
+    let matches = match (current, from) {
+        (Some(pos), Some(expected)) => items[pos] == *expected,
+        (None, None) => true,
+        _ => false,
+    };
+    if !matches {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidData,
+            format!("Entry {} on {} has changed since this action was recorded", change.entry_id, change.date)
+        ));
+    }
+    
+    match (current, to) {
+        (Some(pos), Some(item)) => items[pos] = item.clone(),
+        (Some(pos), None) => {
+            items.remove(pos);
+        }
+        (None, Some(item)) => {
+            let pos = change.position.min(items.len());
+            items.insert(pos, item.clone());
+        }
+        (None, None) => {}
+    }
+    
+    Ok(())
 }
 
 // A single day's log entries
@@ -22,70 +106,359 @@ pub struct FoodLog {
 pub struct DailyLog {
     pub date: String,
     pub entries: Vec<LogEntry>,
-    #[serde(skip)]
-    undo_stack: Vec<UndoAction>, // Not serialized
+    // IDs of the recurrence rules that have already planned entries on this
+    // date, so removing a planned entry does not bring it back
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    applied_rules: Vec<u64>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub exercises: Vec<ExerciseEntry>,
 }
 
 // Represents a single food entry in the log
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub struct LogEntry {
+    #[serde(default)]
+    pub id: u64,          // Unique per user; 0 for components inside a composite entry
     pub food_id: String,  // ID/name of the food
     pub servings: f64,    // Number of servings
     pub calories: f64,    // Pre-calculated calories
+    #[serde(default)]
+    pub price: Option<Price>, // Price per serving when the entry was logged
+    #[serde(default)]
+    pub glycemic_load: Option<f64>, // Glycemic load per serving
+    #[serde(default)]
+    pub macros: Option<Macros>, // Macros per serving when the entry was logged
+    #[serde(default)]
+    pub meal: Meal,
+    #[serde(default)]
+    pub time: Option<String>, // Time of day eaten, HH:MM
+    #[serde(default)]
+    pub status: EntryStatus,
+    // Recipe of a composite food: one entry per component, with servings per
+    // serving of the composite. Empty for basic foods.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub components: Vec<LogEntry>,
 }
 
-// Action type for undo feature
-#[derive(Debug, Clone)]
-enum UndoAction {
-    Add(String, f64),    // (food_id, previous_servings) - Updated to match usage
-    Remove(LogEntry),     // Removed entry to restore
+// Which meal of the day an entry belongs to
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub enum Meal {
+    Breakfast,
+    Lunch,
+    Dinner,
+    Snack,
+    Custom(String),
+    // Entries logged before meals existed. Never offered for new entries,
+    // and not counted towards any meal's share of the target.
+    #[default]
+    Unassigned,
+}
+
+// Whether an entry has been eaten or is only planned
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub enum EntryStatus {
+    #[default]
+    Logged,    // Eaten and logged directly
+    Planned,   // Not eaten yet; not counted in intake
+    Confirmed, // Planned, then confirmed as eaten
+}
+
+impl Meal {
+    pub const STANDARD: [Meal; 4] = [Meal::Breakfast, Meal::Lunch, Meal::Dinner, Meal::Snack];
+
+    pub fn name(&self) -> &str {
+        match self {
+            Meal::Breakfast => "Breakfast",
+            Meal::Lunch => "Lunch",
+            Meal::Dinner => "Dinner",
+            Meal::Snack => "Snack",
+            Meal::Custom(name) => name,
+            Meal::Unassigned => "Unassigned",
+        }
+    }
+}
+
+impl LogEntry {
+    pub fn is_composite(&self) -> bool {
+        !self.components.is_empty()
+    }
+
+    // Planned entries do not count towards intake until confirmed
+    pub fn is_eaten(&self) -> bool {
+        self.status != EntryStatus::Planned
+    }
+
+    // The basic-food entries this entry stands for, scaled by its servings.
+    // A basic-food entry expands to itself.
+    pub fn expand(&self) -> Vec<LogEntry> {
+        if !self.is_composite() {
+            return vec![self.clone()];
+        }
+
+        self.components.iter()
+            .flat_map(|component| component.expand())
+            .map(|mut component| {
+                component.servings *= self.servings;
+                component.meal = self.meal.clone();
+                component.time = self.time.clone();
+                component
+            })
+            .collect()
+    }
+
+    // Total cost of this entry and its currency, if the food was priced.
+    // Composite entries are priced only if all components share a currency.
+    pub fn cost(&self) -> Option<(f64, &str)> {
+        if !self.is_composite() {
+            return self.price.as_ref().map(|p| (p.amount * self.servings, p.currency.as_str()));
+        }
+
+        let mut total = 0.0;
+        let mut currency: Option<&str> = None;
+        for component in &self.components {
+            let (cost, component_currency) = component.cost()?;
+            match currency {
+                Some(c) if c != component_currency => return None,
+                _ => currency = Some(component_currency),
+            }
+            total += cost;
+        }
+        currency.map(|c| (total * self.servings, c))
+    }
+
+    // Snapshot of a basic food at the time it is logged
+    fn from_basic_food(id: u64, food: &BasicFood, servings: f64, meal: Meal, time: Option<String>) -> LogEntry {
+        LogEntry {
+            id,
+            food_id: food.identifier.clone(),
+            servings,
+            calories: food.calories_per_serving,
+            price: food.price.clone(),
+            glycemic_load: food.get_glycemic_load(),
+            macros: food.macros,
+            meal,
+            time,
+            status: EntryStatus::Logged,
+            components: Vec::new(),
+        }
+    }
+
+    // Snapshot of a composite food, keeping its recipe
+    fn from_composite_food(id: u64, food: &CompositeFood, servings: f64, meal: Meal, time: Option<String>) -> LogEntry {
+        LogEntry {
+            id,
+            food_id: food.identifier.clone(),
+            servings,
+            calories: food.get_calories(),
+            price: None, // Derived from the components
+            glycemic_load: food.get_glycemic_load(),
+            macros: food.get_macros(),
+            meal: meal.clone(),
+            time: time.clone(),
+            status: EntryStatus::Logged,
+            components: food.components.iter()
+                .map(|(basic_food, quantity)| LogEntry::from_basic_food(0, basic_food, *quantity, meal.clone(), time.clone()))
+                .collect(),
+        }
+    }
+
+    // Snapshot of a basic or composite food looked up by its identifier
+    fn from_database(id: u64, food_id: &str, servings: f64, meal: Meal, food_db: &FoodDatabase) -> Option<LogEntry> {
+        if let Some(food) = food_db.get_basic_food(food_id) {
+            Some(LogEntry::from_basic_food(id, food, servings, meal, None))
+        } else {
+            food_db.get_composite_food(food_id)
+                .map(|food| LogEntry::from_composite_food(id, food, servings, meal, None))
+        }
+    }
+
+    // Total glycemic load of this entry, if the food has GI data
+    pub fn total_glycemic_load(&self) -> Option<f64> {
+        self.glycemic_load.map(|gl| gl * self.servings)
+    }
+
+    // Total macros of this entry, if the food has macro data
+    pub fn total_macros(&self) -> Option<Macros> {
+        self.macros.map(|macros| macros.scaled(self.servings))
+    }
+}
+
+// A snapshot of one entry before and after an operation. `None` means the
+// entry did not exist, so an add has no `before` and a removal no `after`.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct EntryChange {
+    date: String,
+    entry_id: u64,
+    position: usize, // Index of the entry in the day's log when it exists
+    before: Option<Snapshot>,
+    after: Option<Snapshot>,
+}
+
+// What an entry ID refers to: a food entry or a bout of exercise, which share
+// one ID sequence. Untagged so that histories written before exercise was
+// recorded still load.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(untagged)]
+enum Snapshot {
+    Food(LogEntry),
+    Exercise(ExerciseEntry),
+}
+
+impl Snapshot {
+    fn food(snapshot: &Option<Snapshot>) -> Option<&LogEntry> {
+        match snapshot {
+            Some(Snapshot::Food(entry)) => Some(entry),
+            _ => None,
+        }
+    }
+
+    fn exercise(snapshot: &Option<Snapshot>) -> Option<&ExerciseEntry> {
+        match snapshot {
+            Some(Snapshot::Exercise(exercise)) => Some(exercise),
+            _ => None,
+        }
+    }
+}
+
+// One action in a user's persisted undo/redo history. An action may change
+// several entries (e.g. moving an entry touches two dates); they are applied
+// in order and undone in reverse order.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct HistoryRecord {
+    pub timestamp: String, // YYYY-MM-DD HH:MM:SS
+    pub description: String,
+    changes: Vec<EntryChange>,
+}
+
+// A saved meal that can be logged again with one command. Foods are looked up
+// in the database when the template is applied, so current calories are used.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct MealTemplate {
+    pub name: String,
+    pub meal: Meal,
+    pub items: Vec<(String, f64)>, // (food identifier, servings)
+}
+
+// A meal that is planned automatically on matching weekdays,
+// e.g. oatmeal and coffee every weekday breakfast
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RecurrenceRule {
+    #[serde(default)]
+    pub id: u64, // Shares the ID sequence of the user's food entries
+    pub name: String,
+    pub meal: Meal,
+    pub items: Vec<(String, f64)>, // (food identifier, servings)
+    pub weekdays: Vec<String>,     // Mon, Tue, ...
+    pub start_date: String,        // Earlier dates are never populated
+    #[serde(default)]
+    pub paused: bool,
+}
+
+impl RecurrenceRule {
+    fn applies_to(&self, date: NaiveDate) -> bool {
+        !self.paused
+            && date.format("%Y-%m-%d").to_string() >= self.start_date
+            && self.weekdays.iter().any(|day| day.parse::<Weekday>() == Ok(date.weekday()))
+    }
+
+    // Parse "daily", "weekdays", "weekends" or a comma-separated list like "Mon, Wed, Fri"
+    pub fn parse_weekdays(input: &str) -> Result<Vec<String>, io::Error> {
+        const ALL: [Weekday; 7] = [Weekday::Mon, Weekday::Tue, Weekday::Wed, Weekday::Thu, Weekday::Fri, Weekday::Sat, Weekday::Sun];
+
+        let days: Vec<Weekday> = match input.trim().to_lowercase().as_str() {
+            "daily" => ALL.to_vec(),
+            "weekdays" => ALL[..5].to_vec(),
+            "weekends" => ALL[5..].to_vec(),
+            list => list.split(',')
+                .map(|day| day.trim().parse::<Weekday>().map_err(|_| io::Error::new(
+                    io::ErrorKind::InvalidInput,
+                    format!("Unknown day: '{}'", day.trim())
+                )))
+                .collect::<Result<_, _>>()?,
+        };
+
+        Ok(ALL.iter()
+            .filter(|day| days.contains(day))
+            .map(|day| day.to_string())
+            .collect())
+    }
 }
 
 // Serialization format for the entire log file
 #[derive(Serialize, Deserialize)]
 struct SerializedFoodLog {
-    user_name: String,
+    #[serde(default)]
+    user_id: String,
+    #[serde(default)]
+    next_entry_id: u64,
     daily_logs: Vec<DailyLog>,
+    #[serde(default)]
+    history: Vec<HistoryRecord>,
+    #[serde(default)]
+    redo_stack: Vec<HistoryRecord>,
+    #[serde(default)]
+    templates: Vec<MealTemplate>,
+    #[serde(default)]
+    recurrence_rules: Vec<RecurrenceRule>,
 }
 
 impl FoodLog {
-    pub fn new(user_name: &str) -> Self {
+    pub fn new(user_id: &str) -> Self {
         let today = Local::now().format("%Y-%m-%d").to_string();
         
         Self {
-            user_name: user_name.to_string(),
+            user_id: user_id.to_string(),
             daily_logs: HashMap::new(),
             current_date: today,
-            log_dir_path: "data/logs".to_string(),
+            log_dir_path: LOG_DIR.to_string(),
+            next_entry_id: 1,
+            history: Vec::new(),
+            redo_stack: Vec::new(),
+            pending: None,
+            templates: Vec::new(),
+            recurrence_rules: Vec::new(),
         }
     }
 
-    // Load logs for the specified user
-    pub fn load(&mut self, _food_db: &FoodDatabase) -> Result<(), io::Error> {
-        let log_path = format!("{}/{}_logs.yaml", self.log_dir_path, self.user_name);
+    // Load logs for the specified user and plan today's recurring meals
+    pub fn load(&mut self, food_db: &FoodDatabase) -> Result<(), io::Error> {
+        let log_path = log_file_path(&self.log_dir_path, &self.user_id);
         
-        if Path::new(&log_path).exists() {
-            let contents = fs::read_to_string(&log_path)?;
-            let serialized_log: SerializedFoodLog = serde_yaml::from_str(&contents)
-                .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))?;
-            
-            // Clear existing logs and reset with loaded data
-            self.daily_logs.clear();
-            
-            for log in serialized_log.daily_logs {
-                let daily_log = DailyLog {
-                    date: log.date.clone(),
-                    entries: log.entries,
-                    undo_stack: Vec::new(),
-                };
-                
-                self.daily_logs.insert(log.date, daily_log);
-            }
+        if let Some(serialized_log) = schema::load_versioned::<SerializedFoodLog>(&log_path, SchemaKind::FoodLog)? {
+            self.restore(serialized_log);
         }
         
+        let today = self.current_date.clone();
+        self.plan_recurring_meals(&today, food_db)?;
+        
         Ok(())
     }
 
+    // Read a user's log for a lookup across users. Unlike `load`, this plans
+    // no recurring meals and never writes to the file, even to upgrade it.
+    pub fn read_only(user_id: &str) -> Result<Self, io::Error> {
+        let mut food_log = Self::new(user_id);
+        let log_path = log_file_path(&food_log.log_dir_path, user_id);
+        if let Some(serialized_log) = schema::read_versioned::<SerializedFoodLog>(&log_path, SchemaKind::FoodLog)? {
+            food_log.restore(serialized_log);
+        }
+        Ok(food_log)
+    }
+
+    fn restore(&mut self, serialized_log: SerializedFoodLog) {
+        // Clear existing logs and reset with loaded data
+        self.daily_logs.clear();
+        self.next_entry_id = serialized_log.next_entry_id.max(1);
+        self.history = serialized_log.history;
+        self.redo_stack = serialized_log.redo_stack;
+        self.templates = serialized_log.templates;
+        self.recurrence_rules = serialized_log.recurrence_rules;
+        
+        for log in serialized_log.daily_logs {
+            self.daily_logs.insert(log.date.clone(), log);
+        }
+    }
+
     // Save logs for the current user
     pub fn save(&self) -> Result<(), io::Error> {
         // Ensure log directory exists
@@ -97,14 +470,18 @@ impl FoodLog {
         let logs_vec: Vec<DailyLog> = self.daily_logs.values().cloned().collect();
         
         let serialized_log = SerializedFoodLog {
-            user_name: self.user_name.clone(),
+            user_id: self.user_id.clone(),
+            next_entry_id: self.next_entry_id,
             daily_logs: logs_vec,
+            history: self.history.clone(),
+            redo_stack: self.redo_stack.clone(),
+            templates: self.templates.clone(),
+            recurrence_rules: self.recurrence_rules.clone(),
         };
         
-        let yaml = serde_yaml::to_string(&serialized_log)
-            .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))?;
+        let yaml = schema::to_versioned_yaml(&serialized_log, SchemaKind::FoodLog)?;
         
-        let log_path = format!("{}/{}_logs.yaml", self.log_dir_path, self.user_name);
+        let log_path = log_file_path(&self.log_dir_path, &self.user_id);
         fs::write(&log_path, yaml)?;
         
         Ok(())
@@ -130,224 +507,1301 @@ impl FoodLog {
         Ok(())
     }
     
-    // Add food entry to the current date's log
-    pub fn add_food_entry(&mut self, food: &BasicFood, servings: f64) -> Result<(), io::Error> {
-        // Get or create log for current date
-        let daily_log = self.daily_logs
-            .entry(self.current_date.clone())
-            .or_insert_with(|| DailyLog::new(&self.current_date));
+    // Change the current date and plan any recurring meals due on it
+    pub fn open_date(&mut self, date: &str, food_db: &FoodDatabase) -> Result<usize, io::Error> {
+        self.set_current_date(date)?;
+        self.plan_recurring_meals(date, food_db)
+    }
+    
+    // Add planned entries for every active rule due on a date that has not
+    // been applied there yet. Foods no longer in the database are skipped.
+    // Planning is not an undoable action. Returns the number of entries added.
+    fn plan_recurring_meals(&mut self, date: &str, food_db: &FoodDatabase) -> Result<usize, io::Error> {
+        let Ok(day) = NaiveDate::parse_from_str(date, "%Y-%m-%d") else {
+            return Ok(0);
+        };
         
-        daily_log.add_entry(food, servings);
+        let applied = self.daily_logs.get(date).map(|log| log.applied_rules.clone()).unwrap_or_default();
+        let due: Vec<RecurrenceRule> = self.recurrence_rules.iter()
+            .filter(|rule| rule.applies_to(day) && !applied.contains(&rule.id))
+            .cloned()
+            .collect();
+        if due.is_empty() {
+            return Ok(0);
+        }
         
-        // Save after each modification
-        self.save()?;
+        let mut added = 0;
+        for rule in due {
+            for (food_id, servings) in &rule.items {
+                let Some(mut entry) = LogEntry::from_database(self.next_entry_id, food_id, *servings, rule.meal.clone(), food_db) else {
+                    continue;
+                };
+                entry.status = EntryStatus::Planned;
+                self.next_entry_id += 1;
+                self.daily_log_mut(date).entries.push(entry);
+                added += 1;
+            }
+            self.daily_log_mut(date).applied_rules.push(rule.id);
+        }
         
-        Ok(())
+        self.save()?;
+        Ok(added)
     }
     
-    // Remove food entry from the current date's log
-    pub fn remove_food_entry(&mut self, food_id: &str) -> Result<(), io::Error> {
-        if let Some(daily_log) = self.daily_logs.get_mut(&self.current_date) {
-            daily_log.remove_entry(food_id)?;
-            self.save()?;
-            Ok(())
-        } else {
-            Err(io::Error::new(
-                io::ErrorKind::NotFound,
-                format!("No log found for date: {}", self.current_date)
-            ))
+    // Create a recurrence rule from a saved template. It applies from today on.
+    pub fn add_recurrence_rule(&mut self, name: &str, template_name: &str, weekdays: Vec<String>) -> Result<(), io::Error> {
+        if self.recurrence_rules.iter().any(|r| r.name.eq_ignore_ascii_case(name)) {
+            return Err(io::Error::new(
+                io::ErrorKind::AlreadyExists,
+                format!("A recurring meal named '{}' already exists", name)
+            ));
+        }
+        if weekdays.is_empty() {
+            return Err(io::Error::new(io::ErrorKind::InvalidInput, "No days selected"));
         }
+        let template = self.templates.iter().find(|t| t.name == template_name).ok_or_else(|| io::Error::new(
+            io::ErrorKind::NotFound,
+            format!("Template '{}' not found", template_name)
+        ))?;
+        
+        let rule = RecurrenceRule {
+            id: self.next_entry_id,
+            name: name.to_string(),
+            meal: template.meal.clone(),
+            items: template.items.clone(),
+            weekdays,
+            start_date: Local::now().format("%Y-%m-%d").to_string(),
+            paused: false,
+        };
+        self.next_entry_id += 1;
+        self.recurrence_rules.push(rule);
+        self.save()
     }
     
-    // Undo last action for current date's log
-    pub fn undo(&mut self) -> Result<(), io::Error> {
-        if let Some(daily_log) = self.daily_logs.get_mut(&self.current_date) {
-            daily_log.undo()?;
-            self.save()?;
-            Ok(())
-        } else {
-            Err(io::Error::new(
-                io::ErrorKind::NotFound,
-                format!("No log found for date: {}", self.current_date)
-            ))
-        }
+    fn rule_mut(&mut self, name: &str) -> Result<&mut RecurrenceRule, io::Error> {
+        self.recurrence_rules.iter_mut().find(|r| r.name == name).ok_or_else(|| io::Error::new(
+            io::ErrorKind::NotFound,
+            format!("Recurring meal '{}' not found", name)
+        ))
     }
     
-    // Get the entries for a specific date
-    pub fn get_entries_for_date(&self, date: &str) -> Option<&Vec<LogEntry>> {
-        self.daily_logs.get(date).map(|log| &log.entries)
+    pub fn set_recurrence_rule_paused(&mut self, name: &str, paused: bool) -> Result<(), io::Error> {
+        self.rule_mut(name)?.paused = paused;
+        self.save()
     }
     
-    // Get the current date's log
-    pub fn get_current_log(&self) -> Option<&DailyLog> {
-        self.daily_logs.get(&self.current_date)
+    // Delete a rule. Entries it already planned are kept.
+    pub fn delete_recurrence_rule(&mut self, name: &str) -> Result<(), io::Error> {
+        self.rule_mut(name)?;
+        self.recurrence_rules.retain(|r| r.name != name);
+        self.save()
     }
     
-    // Calculate total calories for a specific date
-    pub fn calculate_calories_for_date(&self, date: &str) -> f64 {
-        if let Some(daily_log) = self.daily_logs.get(date) {
-            daily_log.calculate_total_calories()
-        } else {
-            0.0
-        }
+    pub fn get_recurrence_rules(&self) -> &[RecurrenceRule] {
+        &self.recurrence_rules
     }
     
-    // Get all dates that have logs
-    pub fn get_logged_dates(&self) -> Vec<String> {
-        self.daily_logs.keys().cloned().collect()
+    fn allocate_entry_id(&mut self) -> u64 {
+        let id = self.next_entry_id;
+        self.next_entry_id += 1;
+        id
     }
     
-    // Compares actual calorie intake to target for a given date
-    pub fn compare_to_target(&self, date: &str, user_profile: &UserProfile) -> Option<(f64, f64, f64)> {
-        if let Some(daily_log) = self.daily_logs.get(date) {
-            let actual = daily_log.calculate_total_calories();
-            let target = user_profile.target_calorie;
-            let difference = actual - target;
-            
-            Some((actual, target, difference))
-        } else {
-            None
-        }
+    fn daily_log_mut(&mut self, date: &str) -> &mut DailyLog {
+        self.daily_logs
+            .entry(date.to_string())
+            .or_insert_with(|| DailyLog::new(date))
     }
-}
-
-impl DailyLog {
-    pub fn new(date: &str) -> Self {
-        Self {
-            date: date.to_string(),
-            entries: Vec::new(),
-            undo_stack: Vec::new(),
+    
+    // Move an entry from one snapshot to another, checking that the log
+    // currently holds exactly `from` so that undo and redo are exact
+    fn transition(&mut self, change: &EntryChange, from: &Option<Snapshot>, to: &Option<Snapshot>) -> Result<(), io::Error> {
+        let daily_log = self.daily_log_mut(&change.date);
+        if let Some(Snapshot::Exercise(_)) = from.as_ref().or(to.as_ref()) {
+            replace_item(&mut daily_log.exercises, |e| e.id, change, Snapshot::exercise(from), Snapshot::exercise(to))
+        } else {
+            replace_item(&mut daily_log.entries, |e| e.id, change, Snapshot::food(from), Snapshot::food(to))
         }
     }
     
-    // Add a food entry to this day's log, updating servings if it already exists
-    pub fn add_entry(&mut self, food: &BasicFood, servings: f64) {
-        // Check if this food already exists in today's entries
-        if let Some(existing_entry) = self.entries.iter_mut()
-            .find(|e| e.food_id == food.identifier) {
-            
-            // Store previous servings for undo
-            let prev_servings = existing_entry.servings;
-            self.undo_stack.push(UndoAction::Add(food.identifier.clone(), prev_servings));
-            
-            // Update the servings
-            existing_entry.servings += servings;
+    // Apply changes forwards (before -> after) or backwards (after -> before).
+    // Either all of them are applied or, on error, none are.
+    fn apply_changes(&mut self, changes: &[EntryChange], forward: bool) -> Result<(), io::Error> {
+        let ordered: Vec<&EntryChange> = if forward {
+            changes.iter().collect()
         } else {
-            // If food doesn't exist yet, create a new entry
-            let entry = LogEntry {
-                food_id: food.identifier.clone(),
-                servings,
-                calories: food.calories_per_serving,
+            changes.iter().rev().collect()
+        };
+        
+        for (done, change) in ordered.iter().enumerate() {
+            let result = if forward {
+                self.transition(change, &change.before, &change.after)
+            } else {
+                self.transition(change, &change.after, &change.before)
             };
             
-            self.entries.push(entry);
-            
-            // Add undo action with 0 as previous servings (new item)
-            self.undo_stack.push(UndoAction::Add(food.identifier.clone(), 0.0));
+            if let Err(e) = result {
+                // Roll back what was already applied
+                for applied in ordered[..done].iter().rev() {
+                    let _ = if forward {
+                        self.transition(applied, &applied.after, &applied.before)
+                    } else {
+                        self.transition(applied, &applied.before, &applied.after)
+                    };
+                }
+                return Err(e);
+            }
+        }
+        
+        Ok(())
+    }
+    
+    // Apply a new action, add it to the history and save
+    fn perform(&mut self, description: String, changes: Vec<EntryChange>) -> Result<(), io::Error> {
+        self.apply_changes(&changes, true)?;
+        
+        // Inside a batch the changes are recorded when the batch finishes
+        if let Some(pending) = &mut self.pending {
+            pending.extend(changes);
+            return Ok(());
         }
+        
+        self.record(description, changes)
     }
     
-    // Remove a food entry from this day's log by food_id
-    pub fn remove_entry(&mut self, food_id: &str) -> Result<(), io::Error> {
-        if let Some(pos) = self.entries.iter().position(|e| e.food_id == food_id) {
-            // Store the entry for potential undo
-            let removed_entry = self.entries.remove(pos);
-            self.undo_stack.push(UndoAction::Remove(removed_entry));
-            
-            Ok(())
-        } else {
-            Err(io::Error::new(
-                io::ErrorKind::NotFound,
-                format!("Food '{}' not found in log", food_id)
-            ))
-        }
-    }
-    
-    // Updated undo function to work with new action types
-    pub fn undo(&mut self) -> Result<(), io::Error> {
-        if let Some(action) = self.undo_stack.pop() {
-            match action {
-                UndoAction::Add(food_id, prev_servings) => {
-                    if let Some(entry) = self.entries.iter_mut().find(|e| e.food_id == food_id) {
-                        if prev_servings > 0.0 {
-                            // Was an update, restore previous servings
-                            entry.servings = prev_servings;
-                        } else {
-                            // Was a new entry, remove it
-                            if let Some(pos) = self.entries.iter().position(|e| e.food_id == food_id) {
-                                self.entries.remove(pos);
-                            }
-                        }
-                        Ok(())
-                    } else {
-                        Err(io::Error::new(
-                            io::ErrorKind::InvalidData,
-                            "Invalid undo action: food not found"
-                        ))
-                    }
-                },
-                UndoAction::Remove(entry) => {
-                    self.entries.push(entry);
-                    Ok(())
+    // Add already applied changes to the history as one action and save
+    fn record(&mut self, description: String, changes: Vec<EntryChange>) -> Result<(), io::Error> {
+        // A new action makes anything that was undone unreachable
+        self.redo_stack.clear();
+        self.history.push(HistoryRecord {
+            timestamp: Local::now().format("%Y-%m-%d %H:%M:%S").to_string(),
+            description,
+            changes,
+        });
+        if self.history.len() > MAX_HISTORY {
+            let excess = self.history.len() - MAX_HISTORY;
+            self.history.drain(..excess);
+        }
+        
+        // Save after each modification
+        self.save()
+    }
+    
+    // Run several operations as a single action on the undo history.
+    // If any of them fails, the ones already applied are rolled back.
+    fn batch<T, F>(&mut self, description: String, operations: F) -> Result<T, io::Error>
+    where
+        F: FnOnce(&mut FoodLog) -> Result<T, io::Error>,
+    {
+        if self.pending.is_some() {
+            return operations(self);
+        }
+        
+        self.pending = Some(Vec::new());
+        let result = operations(self);
+        let changes = self.pending.take().unwrap_or_default();
+        
+        match result {
+            Ok(value) => {
+                if !changes.is_empty() {
+                    self.record(description, changes)?;
                 }
+                Ok(value)
+            }
+            Err(e) => {
+                let _ = self.apply_changes(&changes, false);
+                Err(e)
             }
-        } else {
-            Err(io::Error::new(
-                io::ErrorKind::NotFound,
-                "No actions to undo"
-            ))
         }
     }
     
-    // Calculate the total calories for this day
-    pub fn calculate_total_calories(&self) -> f64 {
-        self.entries.iter().map(|e| e.calories * e.servings).sum()
+    // Change recording a new entry appended to a date's log
+    fn addition(&self, date: &str, entry: LogEntry) -> EntryChange {
+        EntryChange {
+            date: date.to_string(),
+            entry_id: entry.id,
+            position: self.daily_logs.get(date).map_or(0, |log| log.entries.len()),
+            before: None,
+            after: Some(Snapshot::Food(entry)),
+        }
     }
-}
-
-// Utility functions for food logs
-
-// Get summary statistics for a date range
-pub fn get_calorie_summary(
-    food_log: &FoodLog, 
-    start_date: &str, 
-    end_date: &str,
-    user_profile: &UserProfile
-) -> Result<Vec<(String, f64, f64, f64)>, io::Error> {
-    // Validate date format
-    if NaiveDate::parse_from_str(start_date, "%Y-%m-%d").is_err() ||
-       NaiveDate::parse_from_str(end_date, "%Y-%m-%d").is_err() {
-        return Err(io::Error::new(
-            io::ErrorKind::InvalidInput,
-            "Invalid date format. Use YYYY-MM-DD."
-        ));
+    
+    // Add food entry to the current date's log, returning the new entry's ID
+    pub fn add_food_entry(&mut self, food: &BasicFood, servings: f64, meal: Meal, time: Option<String>) -> Result<u64, io::Error> {
+        let id = self.allocate_entry_id();
+        let entry = LogEntry::from_basic_food(id, food, servings, meal, time);
+        let description = format!("Added {} x{} to {} on {}", entry.food_id, servings, entry.meal.name(), self.current_date);
+        
+        let change = self.addition(&self.current_date, entry);
+        self.perform(description, vec![change])?;
+        
+        Ok(id)
     }
     
-    let start = NaiveDate::parse_from_str(start_date, "%Y-%m-%d")
-        .expect("Date format already validated");
-    let end = NaiveDate::parse_from_str(end_date, "%Y-%m-%d")
-        .expect("Date format already validated");
+    // Add a composite food to the current date's log as a single entry
+    pub fn add_composite_food_entry(&mut self, food: &CompositeFood, servings: f64, meal: Meal, time: Option<String>) -> Result<u64, io::Error> {
+        let id = self.allocate_entry_id();
+        let entry = LogEntry::from_composite_food(id, food, servings, meal, time);
+        let description = format!("Added {} x{} to {} on {}", entry.food_id, servings, entry.meal.name(), self.current_date);
+        
+        let change = self.addition(&self.current_date, entry);
+        self.perform(description, vec![change])?;
+        
+        Ok(id)
+    }
     
-    if start > end {
-        return Err(io::Error::new(
-            io::ErrorKind::InvalidInput,
-            "Start date cannot be after end date"
-        ));
+    // Plan a food for a date without counting it as eaten
+    pub fn plan_food_entry(&mut self, date: &str, food_id: &str, servings: f64, meal: Meal, food_db: &FoodDatabase) -> Result<u64, io::Error> {
+        if NaiveDate::parse_from_str(date, "%Y-%m-%d").is_err() {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                "Invalid date format. Use YYYY-MM-DD."
+            ));
+        }
+        
+        let id = self.next_entry_id;
+        let mut entry = LogEntry::from_database(id, food_id, servings, meal, food_db).ok_or_else(|| io::Error::new(
+            io::ErrorKind::NotFound,
+            format!("Food '{}' not found in database", food_id)
+        ))?;
+        entry.status = EntryStatus::Planned;
+        self.allocate_entry_id();
+        let description = format!("Planned {} x{} for {} on {}", entry.food_id, servings, entry.meal.name(), date);
+        
+        let change = self.addition(date, entry);
+        self.perform(description, vec![change])?;
+        
+        Ok(id)
     }
     
-    let mut results = Vec::new();
-    let mut current = start;
-    let target = user_profile.target_calorie;
+    // Look up an entry on the current date along with its position
+    fn current_entry(&self, entry_id: u64) -> Result<(usize, LogEntry), io::Error> {
+        self.find_entry(&self.current_date, entry_id)
+    }
     
-    while current <= end {
-        let current_str = current.format("%Y-%m-%d").to_string();
-        let actual = food_log.calculate_calories_for_date(&current_str);
-        let difference = actual - target;
+    fn find_entry(&self, date: &str, entry_id: u64) -> Result<(usize, LogEntry), io::Error> {
+        let daily_log = self.daily_logs.get(date).ok_or_else(|| io::Error::new(
+            io::ErrorKind::NotFound,
+            format!("No log found for date: {}", date)
+        ))?;
+        let pos = daily_log.position(entry_id)?;
+        Ok((pos, daily_log.entries[pos].clone()))
+    }
+    
+    // Replace an entry on the current date with a modified copy
+    fn update_current_entry<F>(&mut self, entry_id: u64, describe: impl FnOnce(&LogEntry) -> String, modify: F) -> Result<(), io::Error>
+    where
+        F: FnOnce(&mut LogEntry),
+    {
+        let (position, before) = self.current_entry(entry_id)?;
+        let mut after = before.clone();
+        modify(&mut after);
+        let description = describe(&before);
         
-        results.push((current_str, actual, target, difference));
+        self.perform(description, vec![EntryChange {
+            date: self.current_date.clone(),
+            entry_id,
+            position,
+            before: Some(Snapshot::Food(before)),
+            after: Some(Snapshot::Food(after)),
+        }])
+    }
+    
+    // Remove a single food entry from the current date's log
+    pub fn remove_food_entry(&mut self, entry_id: u64) -> Result<(), io::Error> {
+        let date = self.current_date.clone();
+        self.remove_entry_on_date(&date, entry_id)
+    }
+    
+    // Remove a single entry from any date's log
+    pub fn remove_entry_on_date(&mut self, date: &str, entry_id: u64) -> Result<(), io::Error> {
+        let (position, entry) = self.find_entry(date, entry_id)?;
+        let description = format!("Removed {} x{} from {} on {}", entry.food_id, entry.servings, entry.meal.name(), date);
         
-        current = current.succ_opt().unwrap(); // Move to next day
+        self.perform(description, vec![EntryChange {
+            date: date.to_string(),
+            entry_id,
+            position,
+            before: Some(Snapshot::Food(entry)),
+            after: None,
+        }])
     }
     
-    Ok(results)
-}
\ No newline at end of file
+    // Change the number of servings of an entry on the current date
+    pub fn update_entry_servings(&mut self, entry_id: u64, servings: f64) -> Result<(), io::Error> {
+        let date = self.current_date.clone();
+        self.update_current_entry(
+            entry_id,
+            |before| format!("Changed {} from x{} to x{} on {}", before.food_id, before.servings, servings, date),
+            |entry| entry.servings = servings,
+        )
+    }
+    
+    // Mark a planned entry on the current date as eaten
+    pub fn confirm_entry(&mut self, entry_id: u64) -> Result<(), io::Error> {
+        let (_, entry) = self.current_entry(entry_id)?;
+        if entry.status != EntryStatus::Planned {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                format!("{} is not a planned entry", entry.food_id)
+            ));
+        }
+        
+        let date = self.current_date.clone();
+        self.update_current_entry(
+            entry_id,
+            |before| format!("Confirmed {} x{} on {}", before.food_id, before.servings, date),
+            |entry| entry.status = EntryStatus::Confirmed,
+        )
+    }
+    
+    // Log exercise on the current date, returning its ID
+    pub fn add_exercise(&mut self, activity: &str, duration_minutes: f64, calories_burned: f64, estimated: bool) -> Result<u64, io::Error> {
+        let id = self.allocate_entry_id();
+        let date = self.current_date.clone();
+        let description = format!("Logged {} ({:.0} min) on {}", activity, duration_minutes, date);
+        let exercise = ExerciseEntry {
+            id,
+            activity: activity.to_string(),
+            duration_minutes,
+            calories_burned,
+            estimated,
+        };
+        
+        let position = self.daily_logs.get(&date).map_or(0, |log| log.exercises.len());
+        self.perform(description, vec![EntryChange {
+            date,
+            entry_id: id,
+            position,
+            before: None,
+            after: Some(Snapshot::Exercise(exercise)),
+        }])?;
+        
+        Ok(id)
+    }
+    
+    // Remove exercise from the current date
+    pub fn remove_exercise(&mut self, exercise_id: u64) -> Result<(), io::Error> {
+        let date = self.current_date.clone();
+        let exercises = self.daily_logs.get(&date).map_or(&[][..], |log| &log.exercises);
+        let position = exercises.iter().position(|e| e.id == exercise_id).ok_or_else(|| io::Error::new(
+            io::ErrorKind::NotFound,
+            format!("Exercise {} not found in log for {}", exercise_id, date)
+        ))?;
+        let exercise = exercises[position].clone();
+        let description = format!("Removed {} ({:.0} min) from {}", exercise.activity, exercise.duration_minutes, date);
+        
+        self.perform(description, vec![EntryChange {
+            date,
+            entry_id: exercise_id,
+            position,
+            before: Some(Snapshot::Exercise(exercise)),
+            after: None,
+        }])
+    }
+    
+    // Confirm every planned entry on the current date as one action.
+    // Returns how many entries were confirmed.
+    pub fn confirm_all_planned(&mut self) -> Result<usize, io::Error> {
+        let planned: Vec<u64> = self.get_current_log()
+            .map(|log| log.entries.iter()
+                .filter(|e| e.status == EntryStatus::Planned)
+                .map(|e| e.id)
+                .collect())
+            .unwrap_or_default();
+        let description = format!("Confirmed {} planned entries on {}", planned.len(), self.current_date);
+        
+        self.batch(description, |log| {
+            for id in &planned {
+                log.confirm_entry(*id)?;
+            }
+            Ok(planned.len())
+        })
+    }
+    
+    // Move an entry on the current date to another meal
+    pub fn move_entry_to_meal(&mut self, entry_id: u64, meal: Meal) -> Result<(), io::Error> {
+        let date = self.current_date.clone();
+        let meal_name = meal.name().to_string();
+        self.update_current_entry(
+            entry_id,
+            |before| format!("Moved {} from {} to {} on {}", before.food_id, before.meal.name(), meal_name, date),
+            |entry| entry.meal = meal,
+        )
+    }
+    
+    // Move an entry from the current date to another date. The entry keeps its ID.
+    pub fn move_entry_to_date(&mut self, entry_id: u64, date: &str) -> Result<(), io::Error> {
+        if NaiveDate::parse_from_str(date, "%Y-%m-%d").is_err() {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                "Invalid date format. Use YYYY-MM-DD."
+            ));
+        }
+        if date == self.current_date {
+            return Ok(());
+        }
+        
+        let (position, entry) = self.current_entry(entry_id)?;
+        let description = format!("Moved {} from {} to {}", entry.food_id, self.current_date, date);
+        
+        let removal = EntryChange {
+            date: self.current_date.clone(),
+            entry_id,
+            position,
+            before: Some(Snapshot::Food(entry.clone())),
+            after: None,
+        };
+        let addition = self.addition(date, entry);
+        
+        self.perform(description, vec![removal, addition])
+    }
+    
+    // Copy all entries of a date, or only those of one meal, onto every date
+    // from `start` to `end` inclusive. Copies keep the original snapshot of
+    // calories and price. Copies onto dates after today have not been eaten
+    // yet, so they are planned and have no time. Returns the number of
+    // entries added.
+    pub fn copy_entries(&mut self, from_date: &str, meal: Option<&Meal>, start: &str, end: &str) -> Result<usize, io::Error> {
+        let (start_date, end_date) = parse_date_range(start, end)?;
+        
+        let source: Vec<LogEntry> = self.get_entries_for_date(from_date)
+            .map(|entries| entries.iter()
+                .filter(|e| meal.is_none_or(|m| e.meal == *m))
+                .cloned()
+                .collect())
+            .unwrap_or_default();
+        if source.is_empty() {
+            return Err(io::Error::new(
+                io::ErrorKind::NotFound,
+                format!("Nothing to copy from {}", from_date)
+            ));
+        }
+        
+        let what = meal.map_or("all entries".to_string(), |m| m.name().to_string());
+        let description = if start == end {
+            format!("Copied {} from {} to {}", what, from_date, start)
+        } else {
+            format!("Copied {} from {} to {} - {}", what, from_date, start, end)
+        };
+        
+        let today = Local::now().date_naive();
+        self.batch(description, |log| {
+            let mut copied = 0;
+            for day in start_date.iter_days().take_while(|d| *d <= end_date) {
+                let date = day.format("%Y-%m-%d").to_string();
+                if date == from_date {
+                    continue;
+                }
+                
+                for entry in &source {
+                    let mut copy = entry.clone();
+                    copy.id = log.allocate_entry_id();
+                    if day > today {
+                        copy.status = EntryStatus::Planned;
+                        copy.time = None;
+                    }
+                    let change = log.addition(&date, copy);
+                    log.perform(String::new(), vec![change])?;
+                    copied += 1;
+                }
+            }
+            Ok(copied)
+        })
+    }
+    
+    // Save the entries of one meal on a date as a named template
+    pub fn save_template(&mut self, name: &str, date: &str, meal: &Meal) -> Result<usize, io::Error> {
+        if self.templates.iter().any(|t| t.name.eq_ignore_ascii_case(name)) {
+            return Err(io::Error::new(
+                io::ErrorKind::AlreadyExists,
+                format!("A template named '{}' already exists", name)
+            ));
+        }
+        
+        let items: Vec<(String, f64)> = self.get_entries_for_date(date)
+            .map(|entries| entries.iter()
+                .filter(|e| e.meal == *meal)
+                .map(|e| (e.food_id.clone(), e.servings))
+                .collect())
+            .unwrap_or_default();
+        if items.is_empty() {
+            return Err(io::Error::new(
+                io::ErrorKind::NotFound,
+                format!("No {} entries on {}", meal.name(), date)
+            ));
+        }
+        
+        let count = items.len();
+        self.templates.push(MealTemplate {
+            name: name.to_string(),
+            meal: meal.clone(),
+            items,
+        });
+        self.save()?;
+        
+        Ok(count)
+    }
+    
+    pub fn delete_template(&mut self, name: &str) -> Result<(), io::Error> {
+        let pos = self.templates.iter().position(|t| t.name == name).ok_or_else(|| io::Error::new(
+            io::ErrorKind::NotFound,
+            format!("Template '{}' not found", name)
+        ))?;
+        self.templates.remove(pos);
+        self.save()
+    }
+    
+    pub fn get_templates(&self) -> &[MealTemplate] {
+        &self.templates
+    }
+    
+    // Log every food of a template on the current date as one undoable action.
+    // Returns the IDs of the new entries.
+    pub fn apply_template(&mut self, name: &str, food_db: &FoodDatabase) -> Result<Vec<u64>, io::Error> {
+        let template = self.templates.iter().find(|t| t.name == name).cloned().ok_or_else(|| io::Error::new(
+            io::ErrorKind::NotFound,
+            format!("Template '{}' not found", name)
+        ))?;
+        let description = format!("Applied template '{}' to {} on {}", template.name, template.meal.name(), self.current_date);
+        
+        self.batch(description, |log| {
+            let mut ids = Vec::new();
+            for (food_id, servings) in &template.items {
+                let id = if let Some(food) = food_db.get_basic_food(food_id) {
+                    log.add_food_entry(food, *servings, template.meal.clone(), None)?
+                } else if let Some(food) = food_db.get_composite_food(food_id) {
+                    log.add_composite_food_entry(food, *servings, template.meal.clone(), None)?
+                } else {
+                    return Err(io::Error::new(
+                        io::ErrorKind::NotFound,
+                        format!("Food '{}' from the template is no longer in the database", food_id)
+                    ));
+                };
+                ids.push(id);
+            }
+            Ok(ids)
+        })
+    }
+    
+    // Undo the most recent action, whichever date it was on.
+    // Returns the description of what was undone.
+    pub fn undo(&mut self) -> Result<String, io::Error> {
+        let record = self.history.pop().ok_or_else(|| io::Error::new(
+            io::ErrorKind::NotFound,
+            "No actions to undo"
+        ))?;
+        
+        if let Err(e) = self.apply_changes(&record.changes, false) {
+            self.history.push(record);
+            return Err(e);
+        }
+        
+        let description = record.description.clone();
+        self.redo_stack.push(record);
+        self.save()?;
+        
+        Ok(description)
+    }
+    
+    // Redo the most recently undone action. Returns its description.
+    pub fn redo(&mut self) -> Result<String, io::Error> {
+        let record = self.redo_stack.pop().ok_or_else(|| io::Error::new(
+            io::ErrorKind::NotFound,
+            "No actions to redo"
+        ))?;
+        
+        if let Err(e) = self.apply_changes(&record.changes, true) {
+            self.redo_stack.push(record);
+            return Err(e);
+        }
+        
+        let description = record.description.clone();
+        self.history.push(record);
+        self.save()?;
+        
+        Ok(description)
+    }
+    
+    // Undo actions until only the first `len` remain in the history.
+    // Returns how many actions were undone.
+    pub fn undo_to(&mut self, len: usize) -> Result<usize, io::Error> {
+        let mut undone = 0;
+        while self.history.len() > len {
+            self.undo()?;
+            undone += 1;
+        }
+        Ok(undone)
+    }
+    
+    // The undo history, oldest first
+    pub fn get_history(&self) -> &[HistoryRecord] {
+        &self.history
+    }
+    
+    pub fn can_redo(&self) -> bool {
+        !self.redo_stack.is_empty()
+    }
+    
+    // Get the entries for a specific date
+    pub fn get_entries_for_date(&self, date: &str) -> Option<&Vec<LogEntry>> {
+        self.daily_logs.get(date).map(|log| &log.entries)
+    }
+    
+    // Get the log for a specific date
+    pub fn get_log_for_date(&self, date: &str) -> Option<&DailyLog> {
+        self.daily_logs.get(date)
+    }
+    
+    // Get the current date's log
+    pub fn get_current_log(&self) -> Option<&DailyLog> {
+        self.daily_logs.get(&self.current_date)
+    }
+    
+    // Get all dates that have logs
+    pub fn get_logged_dates(&self) -> Vec<String> {
+        self.daily_logs.keys().cloned().collect()
+    }
+    
+    // Compares actual calorie intake to target for a given date
+    pub fn compare_to_target(&self, date: &str, user_profile: &UserProfile) -> Option<DaySummary> {
+        self.daily_logs.get(date).map(|daily_log| daily_log.summary(
+            user_profile.target_for_date(date),
+            user_profile.macro_targets_for_date(date)
+        ))
+    }
+}
+
+impl DailyLog {
+    pub fn new(date: &str) -> Self {
+        Self {
+            date: date.to_string(),
+            entries: Vec::new(),
+            applied_rules: Vec::new(),
+            exercises: Vec::new(),
+        }
+    }
+    
+    fn position(&self, id: u64) -> Result<usize, io::Error> {
+        self.entries.iter().position(|e| e.id == id).ok_or_else(|| io::Error::new(
+            io::ErrorKind::NotFound,
+            format!("Entry {} not found in log for {}", id, self.date)
+        ))
+    }
+    
+    // Calculate the total calories for this day
+    pub fn calculate_total_calories(&self) -> f64 {
+        self.entries.iter()
+            .filter(|e| e.is_eaten())
+            .fold(0.0, |total, e| total + e.calories * e.servings)
+    }
+
+    // Calories burned by the day's exercise
+    pub fn calculate_exercise_calories(&self) -> f64 {
+        self.exercises.iter().fold(0.0, |total, e| total + e.calories_burned)
+    }
+
+    // Macros of the eaten entries. Entries without macro data are skipped.
+    pub fn calculate_macros(&self) -> Macros {
+        self.entries.iter()
+            .filter(|e| e.is_eaten())
+            .filter_map(|e| e.total_macros())
+            .fold(Macros::default(), |total, macros| total + macros)
+    }
+
+    // Eaten entries whose food has no macro data
+    pub fn count_missing_macros(&self) -> usize {
+        self.entries.iter()
+            .filter(|e| e.is_eaten() && e.macros.is_none())
+            .count()
+    }
+
+    pub fn summary(&self, target: f64, macro_target: Option<Macros>) -> DaySummary {
+        DaySummary {
+            date: self.date.clone(),
+            intake: self.calculate_total_calories(),
+            exercise: self.calculate_exercise_calories(),
+            target,
+            macros: self.calculate_macros(),
+            macro_target,
+        }
+    }
+
+    // Calories of everything that was planned for this day, eaten or not
+    pub fn calculate_planned_calories(&self) -> f64 {
+        self.entries.iter()
+            .filter(|e| e.status != EntryStatus::Logged)
+            .fold(0.0, |total, e| total + e.calories * e.servings)
+    }
+
+    // Entries grouped by meal: standard meals first, then custom ones in the
+    // order they were first logged, then unassigned ones. Within a meal, timed
+    // entries come first in time order.
+    pub fn entries_by_meal(&self) -> Vec<(Meal, Vec<&LogEntry>)> {
+        let mut meals: Vec<Meal> = Meal::STANDARD.to_vec();
+        for entry in &self.entries {
+            if !meals.contains(&entry.meal) && entry.meal != Meal::Unassigned {
+                meals.push(entry.meal.clone());
+            }
+        }
+        meals.push(Meal::Unassigned);
+
+        meals.into_iter()
+            .filter_map(|meal| {
+                let mut entries: Vec<&LogEntry> = self.entries.iter().filter(|e| e.meal == meal).collect();
+                if entries.is_empty() {
+                    return None;
+                }
+                entries.sort_by(|a, b| match (&a.time, &b.time) {
+                    (Some(a), Some(b)) => a.cmp(b),
+                    (Some(_), None) => std::cmp::Ordering::Less,
+                    (None, Some(_)) => std::cmp::Ordering::Greater,
+                    (None, None) => std::cmp::Ordering::Equal,
+                });
+                Some((meal, entries))
+            })
+            .collect()
+    }
+}
+
+// Utility functions for food logs
+
+// Parse and validate a start/end date pair
+fn parse_date_range(start_date: &str, end_date: &str) -> Result<(NaiveDate, NaiveDate), io::Error> {
+    let (Ok(start), Ok(end)) = (
+        NaiveDate::parse_from_str(start_date, "%Y-%m-%d"),
+        NaiveDate::parse_from_str(end_date, "%Y-%m-%d"),
+    ) else {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidInput,
+            "Invalid date format. Use YYYY-MM-DD."
+        ));
+    };
+
+    if start > end {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidInput,
+            "Start date cannot be after end date"
+        ));
+    }
+
+    Ok((start, end))
+}
+
+// Calorie totals for one day compared with the target
+#[derive(Debug, Clone)]
+pub struct DaySummary {
+    pub date: String,
+    pub intake: f64,   // Gross calories eaten
+    pub exercise: f64, // Calories burned by logged exercise
+    pub target: f64,
+    pub macros: Macros, // Eaten, from entries with macro data
+    pub macro_target: Option<Macros>,
+}
+
+impl DaySummary {
+    // Intake minus exercise
+    pub fn net(&self) -> f64 {
+        self.intake - self.exercise
+    }
+
+    // How far net calories are over (positive) or under the target
+    pub fn difference(&self) -> f64 {
+        self.net() - self.target
+    }
+
+    // Grams of each macro left to reach the targets (negative when over)
+    pub fn remaining_macros(&self) -> Option<Macros> {
+        self.macro_target.map(|target| target - self.macros)
+    }
+}
+
+// Get summary statistics for a date range
+pub fn get_calorie_summary(
+    food_log: &FoodLog, 
+    start_date: &str, 
+    end_date: &str,
+    user_profile: &UserProfile
+) -> Result<Vec<DaySummary>, io::Error> {
+    let (start, end) = parse_date_range(start_date, end_date)?;
+    
+    let mut results = Vec::new();
+    let mut current = start;
+    while current <= end {
+        let current_str = current.format("%Y-%m-%d").to_string();
+        let target = user_profile.target_for_date(&current_str);
+        let macro_target = user_profile.macro_targets_for_date(&current_str);
+        let summary = match food_log.get_log_for_date(&current_str) {
+            Some(daily_log) => daily_log.summary(target, macro_target),
+            None => DailyLog::new(&current_str).summary(target, macro_target),
+        };
+        
+        results.push(summary);
+        
+        current = current.succ_opt().unwrap(); // Move to next day
+    }
+    
+    Ok(results)
+}
+
+// Planned and actual calories for one day, with a per-meal breakdown
+#[derive(Debug)]
+pub struct PlanComparison {
+    pub date: String,
+    pub planned: f64,
+    pub actual: f64,
+    pub target: f64,
+    pub meals: Vec<(Meal, f64, f64)>, // (meal, planned, actual)
+}
+
+// Compare planned with actual calories for a date range. Planned counts every
+// entry that was planned (confirmed or not); actual counts what was eaten.
+pub fn get_plan_comparison(
+    food_log: &FoodLog,
+    start_date: &str,
+    end_date: &str,
+    user_profile: &UserProfile
+) -> Result<Vec<PlanComparison>, io::Error> {
+    let summary = get_calorie_summary(food_log, start_date, end_date, user_profile)?;
+
+    Ok(summary.into_iter()
+        .map(|day| {
+            let DaySummary { date, intake: actual, target, .. } = day;
+            let daily_log = food_log.get_log_for_date(&date);
+            let planned = daily_log.map_or(0.0, |log| log.calculate_planned_calories());
+            let meals = daily_log
+                .map(|log| log.entries_by_meal().into_iter()
+                    .map(|(meal, entries)| {
+                        let calories = |include: fn(&LogEntry) -> bool| entries.iter()
+                            .filter(|e| include(e))
+                            .fold(0.0, |total, e| total + e.calories * e.servings);
+                        let meal_planned = calories(|e| e.status != EntryStatus::Logged);
+                        let meal_actual = calories(|e| e.is_eaten());
+                        (meal, meal_planned, meal_actual)
+                    })
+                    .collect())
+                .unwrap_or_default();
+
+            PlanComparison { date, planned, actual, target, meals }
+        })
+        .collect())
+}
+
+// Get daily food spending in one currency for a date range.
+// Returns (date, spend, calories from priced entries, total calories) per day.
+pub fn get_spend_summary(
+    food_log: &FoodLog,
+    start_date: &str,
+    end_date: &str,
+    currency: &str
+) -> Result<Vec<(String, f64, f64, f64)>, io::Error> {
+    let (start, end) = parse_date_range(start_date, end_date)?;
+
+    let mut results = Vec::new();
+    let mut current = start;
+
+    while current <= end {
+        let current_str = current.format("%Y-%m-%d").to_string();
+        let mut spend = 0.0;
+        let mut priced_calories = 0.0;
+        let mut total_calories = 0.0;
+
+        for entry in food_log.get_entries_for_date(&current_str).into_iter().flatten().filter(|e| e.is_eaten()) {
+            let calories = entry.calories * entry.servings;
+            total_calories += calories;
+            if let Some((cost, entry_currency)) = entry.cost()
+                && entry_currency == currency {
+                spend += cost;
+                priced_calories += calories;
+            }
+        }
+
+        results.push((current_str, spend, priced_calories, total_calories));
+        current = current.succ_opt().unwrap();
+    }
+
+    Ok(results)
+}
+
+// Calories bought per unit of currency for each priced food in a date range,
+// best value first. Returns (food_id, calories, spend).
+pub fn get_cost_efficiency(
+    food_log: &FoodLog,
+    start_date: &str,
+    end_date: &str,
+    currency: &str
+) -> Result<Vec<(String, f64, f64)>, io::Error> {
+    let (start, end) = parse_date_range(start_date, end_date)?;
+
+    let mut totals: HashMap<String, (f64, f64)> = HashMap::new();
+    let mut current = start;
+
+    while current <= end {
+        let current_str = current.format("%Y-%m-%d").to_string();
+        for entry in food_log.get_entries_for_date(&current_str).into_iter().flatten().filter(|e| e.is_eaten()) {
+            if let Some((cost, entry_currency)) = entry.cost()
+                && entry_currency == currency {
+                let total = totals.entry(entry.food_id.clone()).or_insert((0.0, 0.0));
+                total.0 += entry.calories * entry.servings;
+                total.1 += cost;
+            }
+        }
+        current = current.succ_opt().unwrap();
+    }
+
+    let mut results: Vec<(String, f64, f64)> = totals.into_iter()
+        .filter(|(_, (_, spend))| *spend > 0.0)
+        .map(|(food_id, (calories, spend))| (food_id, calories, spend))
+        .collect();
+    results.sort_by(|a, b| (b.1 / b.2).total_cmp(&(a.1 / a.2)));
+
+    Ok(results)
+}
+
+// The currency used by most priced entries in a date range, if any
+pub fn most_used_currency(food_log: &FoodLog, start_date: &str, end_date: &str) -> Option<String> {
+    let (start, end) = parse_date_range(start_date, end_date).ok()?;
+
+    let mut counts: HashMap<&str, usize> = HashMap::new();
+    let mut current = start;
+
+    while current <= end {
+        let current_str = current.format("%Y-%m-%d").to_string();
+        for entry in food_log.get_entries_for_date(&current_str).into_iter().flatten().filter(|e| e.is_eaten()) {
+            if let Some((_, currency)) = entry.cost() {
+                *counts.entry(currency).or_insert(0) += 1;
+            }
+        }
+        current = current.succ_opt().unwrap();
+    }
+
+    counts.into_iter()
+        .max_by_key(|(_, count)| *count)
+        .map(|(currency, _)| currency.to_string())
+}
+
+// Glycemic load eaten on one day
+#[derive(Debug, Clone)]
+pub struct DayGlycemicLoad {
+    pub date: String,
+    pub load: f64,      // From the entries with GI data only
+    pub covered: usize, // Entries with GI data
+    pub missing: usize, // Entries without
+}
+
+impl DayGlycemicLoad {
+    // The day's load, or None when no entry had GI data to go on
+    pub fn known_load(&self) -> Option<f64> {
+        if self.covered > 0 { Some(self.load) } else { None }
+    }
+
+    // Percentage of the day's entries that have GI data, or None if nothing was eaten
+    pub fn coverage(&self) -> Option<f64> {
+        let total = self.covered + self.missing;
+        if total > 0 {
+            Some(self.covered as f64 * 100.0 / total as f64)
+        } else {
+            None
+        }
+    }
+}
+
+// Get daily glycemic load for a date range
+pub fn get_glycemic_summary(
+    food_log: &FoodLog,
+    start_date: &str,
+    end_date: &str
+) -> Result<Vec<DayGlycemicLoad>, io::Error> {
+    let (start, end) = parse_date_range(start_date, end_date)?;
+
+    let mut results = Vec::new();
+    let mut current = start;
+
+    while current <= end {
+        let current_str = current.format("%Y-%m-%d").to_string();
+        let mut day = DayGlycemicLoad { date: current_str.clone(), load: 0.0, covered: 0, missing: 0 };
+
+        for entry in food_log.get_entries_for_date(&current_str).into_iter().flatten().filter(|e| e.is_eaten()) {
+            match entry.total_glycemic_load() {
+                Some(gl) => {
+                    day.load += gl;
+                    day.covered += 1;
+                }
+                None => day.missing += 1,
+            }
+        }
+
+        results.push(day);
+        current = current.succ_opt().unwrap();
+    }
+
+    Ok(results)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::food_database::DietaryInfo;
+    use crate::user_profile::MealSplit;
+
+    // A log that saves into its own temporary directory
+    fn test_log(name: &str) -> FoodLog {
+        let mut log = FoodLog::new(name);
+        log.log_dir_path = std::env::temp_dir()
+            .join(format!("yada_test_{}_{}", name, std::process::id()))
+            .to_string_lossy()
+            .to_string();
+        log.set_current_date("2024-01-01").unwrap();
+        log
+    }
+
+    fn food(identifier: &str, calories: f64) -> BasicFood {
+        BasicFood {
+            identifier: identifier.to_string(),
+            keywords: Vec::new(),
+            calories_per_serving: calories,
+            dietary: DietaryInfo::default(),
+            price: None,
+            glycemic_index: None,
+            available_carbs: None,
+            macros: None,
+        }
+    }
+
+    fn entries(log: &FoodLog, date: &str) -> Vec<LogEntry> {
+        log.get_entries_for_date(date).cloned().unwrap_or_default()
+    }
+
+    fn cleanup(log: &FoodLog) {
+        let _ = fs::remove_dir_all(&log.log_dir_path);
+    }
+
+    #[test]
+    fn undo_and_redo_add() {
+        let mut log = test_log("add");
+        log.add_food_entry(&food("Apple", 95.0), 1.0, Meal::Snack, None).unwrap();
+        let after_first = entries(&log, "2024-01-01");
+        log.add_food_entry(&food("Apple", 95.0), 2.0, Meal::Lunch, None).unwrap();
+        let after_second = entries(&log, "2024-01-01");
+
+        log.undo().unwrap();
+        assert_eq!(entries(&log, "2024-01-01"), after_first);
+
+        log.redo().unwrap();
+        assert_eq!(entries(&log, "2024-01-01"), after_second);
+        cleanup(&log);
+    }
+
+    #[test]
+    fn undo_update_from_zero_servings() {
+        let mut log = test_log("zero");
+        let id = log.add_food_entry(&food("Apple", 95.0), 0.0, Meal::Snack, None).unwrap();
+        let before = entries(&log, "2024-01-01");
+
+        log.update_entry_servings(id, 3.0).unwrap();
+        log.move_entry_to_meal(id, Meal::Dinner).unwrap();
+        log.undo().unwrap();
+        log.undo().unwrap();
+
+        assert_eq!(entries(&log, "2024-01-01"), before);
+        assert_eq!(before[0].servings, 0.0);
+        cleanup(&log);
+    }
+
+    #[test]
+    fn undo_update_only_touches_that_entry() {
+        let mut log = test_log("update");
+        let first = log.add_food_entry(&food("Apple", 95.0), 1.0, Meal::Snack, None).unwrap();
+        log.add_food_entry(&food("Apple", 95.0), 1.0, Meal::Snack, None).unwrap();
+        let second = log.add_food_entry(&food("Apple", 95.0), 1.0, Meal::Snack, None).unwrap();
+        let before = entries(&log, "2024-01-01");
+
+        log.update_entry_servings(second, 4.0).unwrap();
+        log.undo().unwrap();
+
+        assert_eq!(entries(&log, "2024-01-01"), before);
+        assert_ne!(first, second);
+        cleanup(&log);
+    }
+
+    #[test]
+    fn undo_remove_restores_position() {
+        let mut log = test_log("remove");
+        log.add_food_entry(&food("Apple", 95.0), 1.0, Meal::Breakfast, None).unwrap();
+        let middle = log.add_food_entry(&food("Rice", 200.0), 1.5, Meal::Lunch, Some("12:30".to_string())).unwrap();
+        log.add_food_entry(&food("Bread", 80.0), 2.0, Meal::Dinner, None).unwrap();
+        let before = entries(&log, "2024-01-01");
+
+        log.remove_food_entry(middle).unwrap();
+        assert_eq!(entries(&log, "2024-01-01").len(), 2);
+
+        log.undo().unwrap();
+        assert_eq!(entries(&log, "2024-01-01"), before);
+        cleanup(&log);
+    }
+
+    #[test]
+    fn undo_and_redo_composite() {
+        let mut log = test_log("composite");
+        let sandwich = CompositeFood {
+            identifier: "Sandwich".to_string(),
+            keywords: Vec::new(),
+            components: vec![(food("Bread", 80.0), 2.0), (food("Cheese", 110.0), 1.0)],
+            built_from: Vec::new(),
+        };
+
+        log.add_composite_food_entry(&sandwich, 1.5, Meal::Lunch, None).unwrap();
+        let logged = entries(&log, "2024-01-01");
+        assert_eq!(logged[0].components.len(), 2);
+
+        log.undo().unwrap();
+        assert!(entries(&log, "2024-01-01").is_empty());
+
+        log.redo().unwrap();
+        assert_eq!(entries(&log, "2024-01-01"), logged);
+        cleanup(&log);
+    }
+
+    #[test]
+    fn undo_move_between_dates() {
+        let mut log = test_log("move");
+        log.add_food_entry(&food("Apple", 95.0), 1.0, Meal::Snack, None).unwrap();
+        let id = log.add_food_entry(&food("Rice", 200.0), 1.0, Meal::Lunch, None).unwrap();
+        log.add_food_entry(&food("Bread", 80.0), 1.0, Meal::Dinner, None).unwrap();
+        let before = entries(&log, "2024-01-01");
+
+        log.move_entry_to_date(id, "2024-01-02").unwrap();
+        assert_eq!(entries(&log, "2024-01-02").len(), 1);
+
+        log.undo().unwrap();
+        assert_eq!(entries(&log, "2024-01-01"), before);
+        assert!(entries(&log, "2024-01-02").is_empty());
+        cleanup(&log);
+    }
+
+    #[test]
+    fn undo_and_redo_exercise() {
+        let mut log = test_log("exercise");
+        let id = log.add_exercise("Running", 30.0, 300.0, false).unwrap();
+        log.add_food_entry(&food("Apple", 95.0), 1.0, Meal::Snack, None).unwrap();
+        log.remove_exercise(id).unwrap();
+        let exercises = |log: &FoodLog| log.get_log_for_date("2024-01-01").unwrap().exercises.clone();
+        assert!(exercises(&log).is_empty());
+
+        log.undo().unwrap();
+        assert_eq!(exercises(&log)[0].activity, "Running");
+        log.undo().unwrap();
+        log.undo().unwrap();
+        assert!(exercises(&log).is_empty());
+        log.redo().unwrap();
+        assert_eq!(exercises(&log)[0].id, id);
+
+        // Food and exercise snapshots keep their kind when the history is saved
+        let yaml = serde_yaml::to_string(&log.history).unwrap();
+        let history: Vec<HistoryRecord> = serde_yaml::from_str(&yaml).unwrap();
+        assert!(matches!(history[0].changes[0].after, Some(Snapshot::Exercise(_))));
+        cleanup(&log);
+    }
+
+    #[test]
+    fn recreated_rule_with_same_name_is_planned() {
+        let mut log = test_log("rule_ids");
+        let mut food_db = FoodDatabase::new();
+        food_db.basic_foods.push(food("Oats", 150.0));
+        log.add_food_entry(&food("Oats", 150.0), 1.0, Meal::Breakfast, None).unwrap();
+        log.save_template("Oats", "2024-01-01", &Meal::Breakfast).unwrap();
+        let add_rule = |log: &mut FoodLog| {
+            log.add_recurrence_rule("Breakfast", "Oats", vec!["Mon".to_string()]).unwrap();
+            log.recurrence_rules[0].start_date = "2024-01-01".to_string();
+        };
+
+        add_rule(&mut log);
+        assert_eq!(log.open_date("2024-01-08", &food_db).unwrap(), 1);
+        log.delete_recurrence_rule("Breakfast").unwrap();
+        add_rule(&mut log);
+        assert_eq!(log.open_date("2024-01-08", &food_db).unwrap(), 1);
+        assert_eq!(log.open_date("2024-01-08", &food_db).unwrap(), 0);
+        assert_eq!(entries(&log, "2024-01-08").len(), 2);
+        cleanup(&log);
+    }
+
+    #[test]
+    fn undo_copy_as_one_action() {
+        let mut log = test_log("copy");
+        log.add_food_entry(&food("Oats", 150.0), 1.0, Meal::Breakfast, None).unwrap();
+        log.add_food_entry(&food("Milk", 60.0), 2.0, Meal::Breakfast, None).unwrap();
+        log.add_food_entry(&food("Rice", 200.0), 1.0, Meal::Lunch, None).unwrap();
+
+        let copied = log.copy_entries("2024-01-01", Some(&Meal::Breakfast), "2024-01-02", "2024-01-04").unwrap();
+        assert_eq!(copied, 6);
+        let copy = entries(&log, "2024-01-03");
+        assert_eq!(copy.iter().map(|e| e.food_id.as_str()).collect::<Vec<_>>(), ["Oats", "Milk"]);
+
+        log.undo().unwrap();
+        for date in ["2024-01-02", "2024-01-03", "2024-01-04"] {
+            assert!(entries(&log, date).is_empty());
+        }
+        assert_eq!(entries(&log, "2024-01-01").len(), 3);
+
+        log.redo().unwrap();
+        assert_eq!(entries(&log, "2024-01-03"), copy);
+        cleanup(&log);
+    }
+
+    #[test]
+    fn copies_onto_future_dates_are_planned() {
+        let mut log = test_log("copy_future");
+        let today = Local::now().date_naive();
+        let date = |offset: i64| (today + chrono::Duration::days(offset)).format("%Y-%m-%d").to_string();
+        log.set_current_date(&date(0)).unwrap();
+        log.add_food_entry(&food("Oats", 150.0), 1.0, Meal::Breakfast, Some("08:00".to_string())).unwrap();
+
+        log.copy_entries(&date(0), None, &date(-1), &date(2)).unwrap();
+
+        let past = &entries(&log, &date(-1))[0];
+        assert_eq!(past.status, EntryStatus::Logged);
+        assert_eq!(past.time.as_deref(), Some("08:00"));
+        for offset in [1, 2] {
+            let future = &entries(&log, &date(offset))[0];
+            assert_eq!(future.status, EntryStatus::Planned);
+            assert_eq!(future.time, None);
+        }
+        cleanup(&log);
+    }
+
+    #[test]
+    fn undo_refuses_when_entry_changed() {
+        let mut log = test_log("changed");
+        let id = log.add_food_entry(&food("Apple", 95.0), 1.0, Meal::Snack, None).unwrap();
+        log.update_entry_servings(id, 2.0).unwrap();
+
+        // Change the entry behind the history's back
+        log.daily_logs.get_mut("2024-01-01").unwrap().entries[0].servings = 5.0;
+        let current = entries(&log, "2024-01-01");
+
+        assert!(log.undo().is_err());
+        assert_eq!(entries(&log, "2024-01-01"), current);
+        assert_eq!(log.get_history().len(), 2);
+        cleanup(&log);
+    }
+
+    #[test]
+    fn entries_without_a_meal_stay_unassigned() {
+        let daily_log: DailyLog = serde_yaml::from_str("\
+date: 2024-01-01
+entries:
+- food_id: Apple
+  servings: 1.0
+  calories: 95.0
+- food_id: Rice
+  servings: 1.0
+  calories: 200.0
+  meal: Lunch
+").unwrap();
+
+        assert_eq!(daily_log.entries[0].meal, Meal::Unassigned);
+        let meals: Vec<Meal> = daily_log.entries_by_meal().into_iter().map(|(meal, _)| meal).collect();
+        assert_eq!(meals, vec![Meal::Lunch, Meal::Unassigned]);
+        assert_eq!(MealSplit::default().target_for(&Meal::Unassigned, 2000.0), None);
+    }
+}
@@ -0,0 +1,274 @@
+use std::io;
+use chrono::NaiveDate;
+use serde::{Serialize, Deserialize};
+
+use crate::food_log::{FoodLog, get_calorie_summary};
+use crate::tdee::{CALORIES_PER_KG, is_complete_day};
+use crate::user_profile::{Gender, UserProfile};
+
+// Fastest weekly loss considered safe, as a share of body weight
+const MAX_WEEKLY_LOSS_FRACTION: f64 = 0.01;
+// Fastest weekly gain in kg that is mostly lean mass
+const MAX_WEEKLY_GAIN: f64 = 0.5;
+// Goals within this many kg of the current weight count as reached
+const GOAL_TOLERANCE: f64 = 0.2;
+
+// Lowest daily target we will set without medical supervision
+pub fn minimum_calories(gender: &Gender) -> f64 {
+    match gender {
+        Gender::Male => 1500.0,
+        Gender::Female => 1200.0,
+    }
+}
+
+// How fast to move towards the goal weight
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub enum GoalPace {
+    WeeklyRate(f64),    // kg per week
+    TargetDate(String), // YYYY-MM-DD
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct WeightGoal {
+    pub target_weight: f64,
+    pub pace: GoalPace,
+}
+
+// The daily target a goal works out to
+#[derive(Debug, Clone)]
+pub struct GoalPlan {
+    pub maintenance: f64,
+    pub target: f64,
+    pub weekly_rate: f64,   // Planned change in kg per week, negative when losing
+    pub achievable: bool,   // False when the safety limits leave no way to reach the goal
+    pub notes: Vec<String>, // Safety limits that were applied
+}
+
+impl WeightGoal {
+    pub fn describe(&self) -> String {
+        match &self.pace {
+            GoalPace::WeeklyRate(rate) => format!("{:.1} kg at {:.2} kg per week", self.target_weight, rate.abs()),
+            GoalPace::TargetDate(date) => format!("{:.1} kg by {}", self.target_weight, date),
+        }
+    }
+
+    // Work out the daily target from maintenance calories, keeping the rate of
+    // change and the target itself within safe limits
+    pub fn plan(&self, weight: f64, maintenance: f64, gender: &Gender, today: NaiveDate) -> GoalPlan {
+        let difference = self.target_weight - weight;
+        if difference.abs() <= GOAL_TOLERANCE {
+            return GoalPlan {
+                maintenance,
+                target: maintenance,
+                weekly_rate: 0.0,
+                achievable: true,
+                notes: vec!["Goal weight reached; eating at maintenance.".to_string()],
+            };
+        }
+
+        // Raising the target to the minimum would turn a loss into a gain
+        let minimum = minimum_calories(gender);
+        if difference < 0.0 && maintenance <= minimum {
+            return GoalPlan {
+                maintenance,
+                target: maintenance,
+                weekly_rate: 0.0,
+                achievable: false,
+                notes: vec![format!(
+                    "Maintenance is already at or below the safe minimum of {:.0} calories, so this goal cannot be met safely by eating less. Eating at maintenance; consider more activity or advice from a professional.",
+                    minimum
+                )],
+            };
+        }
+
+        let mut notes = Vec::new();
+        let requested = match &self.pace {
+            GoalPace::WeeklyRate(rate) => rate.abs() * difference.signum(),
+            GoalPace::TargetDate(date) => match NaiveDate::parse_from_str(date, "%Y-%m-%d") {
+                Ok(goal_date) if goal_date > today => difference / ((goal_date - today).num_days() as f64 / 7.0),
+                _ => {
+                    notes.push("The goal date has passed; using the fastest safe rate.".to_string());
+                    f64::INFINITY * difference.signum()
+                }
+            },
+        };
+
+        let max_loss = weight * MAX_WEEKLY_LOSS_FRACTION;
+        let mut weekly_rate = requested.clamp(-max_loss, MAX_WEEKLY_GAIN);
+        if weekly_rate != requested && requested.is_finite() {
+            notes.push(format!("Rate limited to {:+.2} kg per week for safety.", weekly_rate));
+        }
+
+        let mut target = maintenance + weekly_rate * CALORIES_PER_KG / 7.0;
+        if target < minimum {
+            target = minimum;
+            weekly_rate = (target - maintenance) * 7.0 / CALORIES_PER_KG;
+            notes.push(format!("Target raised to the safe minimum of {:.0} calories.", minimum));
+        }
+
+        GoalPlan {
+            maintenance,
+            target: target.round(),
+            weekly_rate,
+            achievable: true,
+            notes,
+        }
+    }
+}
+
+// Where the user is heading at the intake they have actually logged
+#[derive(Debug, Clone)]
+pub enum GoalProjection {
+    Reached,
+    OnTrack { date: String, weekly_rate: f64 },
+    NotProgressing { weekly_rate: f64 },
+}
+
+// Project when the goal will be reached from the average net intake over
+// the fully logged days in `window_days` ending on `today`. Returns Ok(None) when
+// the user has no goal or nothing was logged.
+pub fn project_goal(
+    food_log: &FoodLog,
+    user_profile: &UserProfile,
+    today: &str,
+    window_days: i64
+) -> Result<Option<GoalProjection>, io::Error> {
+    let Some(goal) = &user_profile.goal else {
+        return Ok(None);
+    };
+    let end = NaiveDate::parse_from_str(today, "%Y-%m-%d").map_err(|_| io::Error::new(
+        io::ErrorKind::InvalidInput,
+        "Invalid date format. Use YYYY-MM-DD."
+    ))?;
+    let start_date = (end - chrono::Duration::days(window_days - 1)).format("%Y-%m-%d").to_string();
+
+    let logged: Vec<f64> = get_calorie_summary(food_log, &start_date, today, user_profile)?
+        .iter()
+        .filter(|day| is_complete_day(day))
+        .map(|day| day.net())
+        .collect();
+
+    Ok(project_from(&logged, goal, user_profile.weight, user_profile.maintenance_calories(), end))
+}
+
+// The projection from the net intake of each fully logged day
+fn project_from(logged: &[f64], goal: &WeightGoal, weight: f64, maintenance: f64, today: NaiveDate) -> Option<GoalProjection> {
+    if logged.is_empty() {
+        return None;
+    }
+
+    let difference = goal.target_weight - weight;
+    if difference.abs() <= GOAL_TOLERANCE {
+        return Some(GoalProjection::Reached);
+    }
+
+    let average_intake = logged.iter().fold(0.0, |total, intake| total + intake) / logged.len() as f64;
+    let weekly_rate = (average_intake - maintenance) * 7.0 / CALORIES_PER_KG;
+    if weekly_rate == 0.0 || weekly_rate.signum() != difference.signum() {
+        return Some(GoalProjection::NotProgressing { weekly_rate });
+    }
+
+    let days = (difference / weekly_rate * 7.0).ceil() as i64;
+    let date = (today + chrono::Duration::days(days)).format("%Y-%m-%d").to_string();
+    Some(GoalProjection::OnTrack { date, weekly_rate })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn today() -> NaiveDate {
+        NaiveDate::from_ymd_opt(2024, 1, 1).unwrap()
+    }
+
+    fn goal(target_weight: f64, pace: GoalPace) -> WeightGoal {
+        WeightGoal { target_weight, pace }
+    }
+
+    #[test]
+    fn requested_rate_is_used_when_safe() {
+        let plan = goal(75.0, GoalPace::WeeklyRate(0.5)).plan(80.0, 2500.0, &Gender::Male, today());
+        assert_eq!(plan.weekly_rate, -0.5);
+        assert_eq!(plan.target, 1950.0);
+        assert!(plan.achievable);
+        assert!(plan.notes.is_empty());
+    }
+
+    #[test]
+    fn loss_rate_is_clamped_to_one_percent() {
+        let plan = goal(70.0, GoalPace::WeeklyRate(2.0)).plan(80.0, 3000.0, &Gender::Male, today());
+        assert!((plan.weekly_rate + 0.8).abs() < 1e-9);
+        assert_eq!(plan.target, 2120.0);
+        assert_eq!(plan.notes.len(), 1);
+    }
+
+    #[test]
+    fn gain_rate_is_clamped() {
+        let plan = goal(90.0, GoalPace::WeeklyRate(1.0)).plan(80.0, 2500.0, &Gender::Male, today());
+        assert_eq!(plan.weekly_rate, MAX_WEEKLY_GAIN);
+        assert_eq!(plan.target, 3050.0);
+    }
+
+    #[test]
+    fn target_is_raised_to_the_minimum() {
+        let plan = goal(60.0, GoalPace::WeeklyRate(0.6)).plan(70.0, 1600.0, &Gender::Female, today());
+        assert_eq!(plan.target, 1200.0);
+        assert!(plan.weekly_rate < 0.0);
+        assert!((plan.weekly_rate - (1200.0 - 1600.0) * 7.0 / CALORIES_PER_KG).abs() < 1e-9);
+        assert!(plan.achievable);
+    }
+
+    #[test]
+    fn loss_below_minimum_maintenance_is_not_achievable() {
+        let plan = goal(50.0, GoalPace::WeeklyRate(0.5)).plan(55.0, 1150.0, &Gender::Female, today());
+        assert!(!plan.achievable);
+        assert_eq!(plan.weekly_rate, 0.0);
+        assert_eq!(plan.target, 1150.0);
+
+        // Gaining is still fine
+        let plan = goal(60.0, GoalPace::WeeklyRate(0.25)).plan(55.0, 1150.0, &Gender::Female, today());
+        assert!(plan.achievable);
+        assert!(plan.weekly_rate > 0.0);
+    }
+
+    #[test]
+    fn target_date_sets_the_rate() {
+        // 2 kg over 4 weeks
+        let plan = goal(78.0, GoalPace::TargetDate("2024-01-29".to_string())).plan(80.0, 2500.0, &Gender::Male, today());
+        assert!((plan.weekly_rate + 0.5).abs() < 1e-9);
+        assert_eq!(plan.target, 1950.0);
+    }
+
+    #[test]
+    fn past_goal_date_uses_fastest_safe_rate() {
+        for date in ["2023-12-01", "2024-01-01"] {
+            let plan = goal(70.0, GoalPace::TargetDate(date.to_string())).plan(80.0, 3000.0, &Gender::Male, today());
+            assert!((plan.weekly_rate + 0.8).abs() < 1e-9);
+            assert!(plan.notes[0].contains("passed"));
+        }
+    }
+
+    #[test]
+    fn projection_from_logged_intake() {
+        let goal = goal(78.0, GoalPace::WeeklyRate(0.5));
+        // 1100 calories a day under maintenance is 1 kg a week
+        match project_from(&[1400.0, 1400.0], &goal, 80.0, 2500.0, today()) {
+            Some(GoalProjection::OnTrack { date, weekly_rate }) => {
+                assert_eq!(date, "2024-01-15");
+                assert!((weekly_rate + 1.0).abs() < 1e-9);
+            }
+            other => panic!("unexpected projection {:?}", other),
+        }
+        assert!(matches!(project_from(&[2600.0], &goal, 80.0, 2500.0, today()),
+            Some(GoalProjection::NotProgressing { .. })));
+        assert!(matches!(project_from(&[2000.0], &goal, 78.1, 2500.0, today()), Some(GoalProjection::Reached)));
+        assert!(project_from(&[], &goal, 80.0, 2500.0, today()).is_none());
+    }
+
+    #[test]
+    fn goal_within_tolerance_is_reached() {
+        let plan = goal(80.1, GoalPace::WeeklyRate(0.5)).plan(80.0, 2500.0, &Gender::Male, today());
+        assert_eq!(plan.target, 2500.0);
+        assert_eq!(plan.weekly_rate, 0.0);
+    }
+}
@@ -0,0 +1,183 @@
+use std::fmt;
+use std::io;
+use std::ops::RangeInclusive;
+use std::str::FromStr;
+
+// Typing this at any prompt backs out of the current operation
+pub const CANCEL_WORD: &str = "cancel";
+
+// Plausible ranges for profile values
+pub const HEIGHT_RANGE: RangeInclusive<f64> = 50.0..=250.0; // cm
+pub const WEIGHT_RANGE: RangeInclusive<f64> = 20.0..=400.0; // kg
+pub const AGE_RANGE: RangeInclusive<u32> = 18..=120; // The calorie formulas are for adults
+
+// Why a prompt did not produce a value
+#[derive(Debug)]
+pub enum InputError {
+    Cancelled,      // The user typed the cancel word
+    Closed,         // Standard input reached end of file
+    Io(io::Error),
+}
+
+impl fmt::Display for InputError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            InputError::Cancelled => write!(f, "cancelled"),
+            InputError::Closed => write!(f, "input ended"),
+            InputError::Io(e) => write!(f, "could not read input: {}", e),
+        }
+    }
+}
+
+impl std::error::Error for InputError {}
+
+impl From<io::Error> for InputError {
+    fn from(e: io::Error) -> Self {
+        InputError::Io(e)
+    }
+}
+
+// For prompts inside functions that report plain I/O errors
+impl From<InputError> for io::Error {
+    fn from(e: InputError) -> Self {
+        match e {
+            InputError::Io(e) => e,
+            InputError::Cancelled => io::Error::new(io::ErrorKind::Interrupted, e.to_string()),
+            InputError::Closed => io::Error::new(io::ErrorKind::UnexpectedEof, e.to_string()),
+        }
+    }
+}
+
+// Print a prompt and read one trimmed line
+pub fn read_line(prompt: &str) -> Result<String, InputError> {
+    println!("{}", prompt);
+    let mut input = String::new();
+    if io::stdin().read_line(&mut input)? == 0 {
+        return Err(InputError::Closed);
+    }
+
+    let input = input.trim();
+    if input.eq_ignore_ascii_case(CANCEL_WORD) {
+        return Err(InputError::Cancelled);
+    }
+    Ok(input.to_string())
+}
+
+// Non-empty text, asking again until some is given
+pub fn read_text(prompt: &str) -> Result<String, InputError> {
+    loop {
+        let input = read_line(prompt)?;
+        if !input.is_empty() {
+            return Ok(input);
+        }
+        println!("Please enter a value (or '{}' to go back).", CANCEL_WORD);
+    }
+}
+
+// A number within `range`, asking again until one is given
+pub fn read_number<T>(prompt: &str, range: RangeInclusive<T>) -> Result<T, InputError>
+where
+    T: FromStr + PartialOrd + fmt::Display + Clone,
+{
+    loop {
+        if let Some(value) = read_optional_number(prompt, range.clone())? {
+            return Ok(value);
+        }
+        println!("Please enter a number (or '{}' to go back).", CANCEL_WORD);
+    }
+}
+
+// Like `read_number`, but an empty line gives None
+pub fn read_optional_number<T>(prompt: &str, range: RangeInclusive<T>) -> Result<Option<T>, InputError>
+where
+    T: FromStr + PartialOrd + fmt::Display,
+{
+    loop {
+        let input = read_line(prompt)?;
+        if input.is_empty() {
+            return Ok(None);
+        }
+        match input.parse::<T>() {
+            Ok(value) if range.contains(&value) => return Ok(Some(value)),
+            Ok(_) => println!("Please enter a value between {} and {}.", range.start(), range.end()),
+            Err(_) => println!("'{}' is not a number.", input),
+        }
+    }
+}
+
+// Pick one of `options` by number, asking again until a valid choice is made
+pub fn read_choice<T: Clone>(prompt: &str, options: &[(&str, T)]) -> Result<T, InputError> {
+    loop {
+        println!("{}", prompt);
+        print_options(options);
+        let input = read_line(&format!("Enter a number from 1 to {} (or '{}' to go back):", options.len(), CANCEL_WORD))?;
+        match parse_choice(&input, options) {
+            Some(choice) => return Ok(choice),
+            None => println!("Invalid choice, please try again."),
+        }
+    }
+}
+
+// Like `read_choice`, but an empty line gives None
+pub fn read_optional_choice<T: Clone>(prompt: &str, options: &[(&str, T)]) -> Result<Option<T>, InputError> {
+    loop {
+        println!("{}", prompt);
+        print_options(options);
+        let input = read_line(&format!("Enter a number from 1 to {} (or press Enter to skip):", options.len()))?;
+        if input.is_empty() {
+            return Ok(None);
+        }
+        match parse_choice(&input, options) {
+            Some(choice) => return Ok(Some(choice)),
+            None => println!("Invalid choice, please try again."),
+        }
+    }
+}
+
+fn print_options<T>(options: &[(&str, T)]) {
+    for (i, (label, _)) in options.iter().enumerate() {
+        println!("{}: {}", i + 1, label);
+    }
+}
+
+fn parse_choice<T: Clone>(input: &str, options: &[(&str, T)]) -> Option<T> {
+    match input.parse::<usize>() {
+        Ok(choice) if choice >= 1 && choice <= options.len() => Some(options[choice - 1].1.clone()),
+        _ => None,
+    }
+}
+
+// A y/n answer, asking again until one is given
+pub fn read_yes_no(prompt: &str) -> Result<bool, InputError> {
+    loop {
+        let input = read_line(&format!("{} (y/n):", prompt))?;
+        if let Some(answer) = parse_yes_no(&input) {
+            return Ok(answer);
+        }
+        println!("Please answer y or n.");
+    }
+}
+
+// Like `read_yes_no`, but an empty line means the answer is not known
+pub fn read_optional_yes_no(prompt: &str) -> Result<Option<bool>, InputError> {
+    loop {
+        let input = read_line(&format!("{} (y/n, or press Enter if unknown):", prompt))?;
+        if input.is_empty() {
+            return Ok(None);
+        }
+        if let Some(answer) = parse_yes_no(&input) {
+            return Ok(Some(answer));
+        }
+        println!("Please answer y or n, or press Enter if unknown.");
+    }
+}
+
+fn parse_yes_no(input: &str) -> Option<bool> {
+    if input.eq_ignore_ascii_case("y") || input.eq_ignore_ascii_case("yes") {
+        Some(true)
+    } else if input.eq_ignore_ascii_case("n") || input.eq_ignore_ascii_case("no") {
+        Some(false)
+    } else {
+        None
+    }
+}
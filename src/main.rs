@@ -1,50 +1,133 @@
 mod user_profile;
 mod food_database;
 mod food_log;
+mod schema;
+mod exercise;
+mod weight_journal;
+mod tdee;
+mod goal;
+mod calorie_strategy;
+mod calorie_budget;
+mod input;
 
-use user_profile::{create_user, load_users, modify_user, save_users, select_user, UserProfile};
-use food_database::FoodDatabase;
-use food_log::{FoodLog, get_calorie_summary};
-use std::io;
+use user_profile::{create_user, delete_user, load_users, modify_user, rename_user, save_users, select_user, UserProfile};
+use calorie_strategy::TargetCalorieCalcStrategy;
+use calorie_budget::weekly_budget_status;
+use input::InputError;
+use food_database::{Allergen, BasicFood, DietaryInfo, FoodDatabase, Macros, Price};
+use exercise::{ACTIVITIES, estimate_calories_burned, met_for};
+use tdee::{Confidence, estimate_tdee};
+use goal::{GoalPace, GoalProjection, WeightGoal, project_goal};
+use food_log::{DailyLog, DaySummary, FoodLog, LogEntry, Meal, RecurrenceRule, get_calorie_summary, get_plan_comparison, get_cost_efficiency, get_glycemic_summary, get_spend_summary, most_used_currency};
 
 fn main() {
-    let mut users = load_users();
+    let mut users = match load_users() {
+        Ok(users) => users,
+        Err(e) => {
+            println!("Error: Could not load users: {}", e);
+            return;
+        }
+    };
 
+    if let Err(e) = main_menu(&mut users) {
+        // Nothing more can be read, so keep what was entered
+        save_users(&users);
+        println!("Stopping ({}). Users saved. Exiting...", e);
+    }
+}
+
+// Returns an error once no more input can be read
+fn main_menu(users: &mut Vec<UserProfile>) -> Result<(), InputError> {
     loop {
         println!("\nUser Management System");
         println!("1. List Users");
         println!("2. Add New User");
         println!("3. Modify Existing User");
         println!("4. User Session");
-        println!("5. Save and Exit");
-        println!("Select an option (1-5): ");
+        println!("5. Rename User");
+        println!("6. Delete User");
+        println!("7. Save and Exit");
 
-        let mut input = String::new();
-        io::stdin().read_line(&mut input).expect("Failed to read input");
-        match input.trim() {
+        let choice = match input::read_line("Select an option (1-7): ") {
+            Ok(choice) => choice,
+            Err(InputError::Cancelled) => continue,
+            Err(e) => return Err(e),
+        };
+        match choice.as_str() {
             "1" => {
-                list_users(&users);
+                list_users(users);
             }
             "2" => {
-                let new_user = create_user();
-                users.push(new_user);
-                println!("User added successfully.");
+                match create_user(users) {
+                    Ok(new_user) => {
+                        users.push(new_user);
+                        println!("User added successfully.");
+                    }
+                    Err(InputError::Cancelled) => println!("User creation cancelled."),
+                    Err(e) => return Err(e),
+                }
             }
             "3" => {
-                if let Some(index) = select_user(&users) {
-                    modify_user(&mut users[index]);
+                if let Some(index) = select_user(users)? {
+                    modify_user(&mut users[index])?;
                     println!("User modified successfully.");
                 } else {
                     println!("No user selected.");
                 }
             }
             "4" => {
-                user_session(&mut users);
+                back_on_cancel(user_session(users))?;
             }
             "5" => {
-                save_users(&users);
+                if let Some(index) = select_user(users)? {
+                    let name = match input::read_text(&format!("Enter new name for {}:", users[index].name)) {
+                        Ok(name) => name,
+                        Err(InputError::Cancelled) => {
+                            println!("Rename cancelled.");
+                            continue;
+                        }
+                        Err(e) => return Err(e),
+                    };
+                    match rename_user(users, index, &name) {
+                        Ok(_) => {
+                            save_users(users);
+                            println!("User renamed to {}. Their food log is kept.", users[index].name);
+                        }
+                        Err(e) => println!("Could not rename user: {}", e),
+                    }
+                } else {
+                    println!("No user selected.");
+                }
+            }
+            "6" => {
+                if let Some(index) = select_user(users)? {
+                    let question = format!("Delete {}? Their food log will be moved to the archive.", users[index].name);
+                    match input::read_yes_no(&question) {
+                        Ok(true) => {}
+                        Ok(false) | Err(InputError::Cancelled) => {
+                            println!("User not deleted.");
+                            continue;
+                        }
+                        Err(e) => return Err(e),
+                    }
+                    match delete_user(users, index) {
+                        Ok((user, archived)) => {
+                            save_users(users);
+                            match archived {
+                                Some(path) => println!("Deleted {}. Food log archived to '{}'.", user.name, path),
+                                None => println!("Deleted {}.", user.name),
+                            }
+                        }
+                        Err(e) => println!("Could not delete user: {}", e),
+                    }
+                } else {
+                    println!("No user selected.");
+                }
+            }
+            "7" => {
+                save_users(users);
                 println!("Users saved. Exiting...");
-                break;
+                return Ok(());
             }
             _ => println!("Invalid option. Please try again."),
         }
@@ -62,127 +145,396 @@ fn list_users(users: &[UserProfile]) {
     }
 }
 
-fn user_session(users: &mut Vec<UserProfile>) {
+// Returns an error once no more input can be read, after saving the
+// food database and log
+fn user_session(users: &mut [UserProfile]) -> Result<(), InputError> {
     if users.is_empty() {
         println!("No users available. Please add a user first.");
-        return;
+        return Ok(());
     }
 
     // Initialize food database
     let mut food_db = FoodDatabase::new();
     if let Err(e) = food_db.load() {
+        if schema::is_unsupported_version(&e) {
+            // Continuing would overwrite the newer file on save, so stop here
+            println!("Error: Could not load food database: {}", e);
+            return Ok(());
+        }
         println!("Warning: Could not load food database: {}", e);
     }
 
     // Initial user selection
     println!("\nStarting user session");
-    let mut selected_index = match select_user(users) {
+    let mut selected_index = match select_user(users)? {
         Some(index) => index,
         None => {
             println!("No user selected. Exiting session.");
-            return;
+            return Ok(());
         }
     };
 
     // Initialize food log for the selected user
-    let mut food_log = FoodLog::new(&users[selected_index].name);
+    let mut food_log = FoodLog::new(&users[selected_index].id);
     if let Err(e) = food_log.load(&food_db) {
+        if schema::is_unsupported_version(&e) {
+            println!("Error: Could not load food log: {}", e);
+            return Ok(());
+        }
         println!("Warning: Could not load food log: {}", e);
     }
 
+    // Goals paced by a date change the target every day, so bring it up to date
+    users[selected_index].recalculate_target();
     println!("Selected user: {}", users[selected_index].name);
-    
+
+    let result = session_menu(users, &mut selected_index, &mut food_db, &mut food_log);
+
+    // Save food database and log before exiting
+    if let Err(e) = food_db.save() {
+        println!("Warning: Failed to save food database: {}", e);
+    }
+    if let Err(e) = food_log.save() {
+        println!("Warning: Failed to save food log: {}", e);
+    }
+    println!("Exiting user session.");
+    result
+}
+
+fn session_menu(users: &mut [UserProfile], selected_index: &mut usize, food_db: &mut FoodDatabase, food_log: &mut FoodLog) -> Result<(), InputError> {
     loop {
-        println!("\nUser Session - Current user: {}", users[selected_index].name);
+        println!("\nUser Session - Current user: {}", users[*selected_index].name);
         println!("1. List All Users");
         println!("2. Change Selected User");
         println!("3. Modify Current User");
         println!("4. Food Database Management");
         println!("5. Food Log Management");
         println!("6. View Statistics and Reports");
-        println!("7. Exit Session");
+        println!("7. Meal Planning");
+        println!("8. Weight Journal");
+        println!("9. Adaptive Maintenance Estimate");
+        println!("10. Weight Goal");
+        println!("11. Exit Session");
 
-        let mut input = String::new();
-        io::stdin().read_line(&mut input).expect("Failed to read input");
-        
-        match input.trim() {
+        let choice = input::read_line("Enter your choice: ")?;
+        match choice.as_str() {
             "1" => {
                 list_users(users);
             }
             "2" => {
                 list_users(users);
-                if let Some(index) = select_user(users) {
+                if let Some(index) = select_user(users)? {
                     // Save current user's food log before switching
                     if let Err(e) = food_log.save() {
                         println!("Warning: Failed to save food log: {}", e);
                     }
                     
                     // Switch user and load their food log
-                    selected_index = index;
-                    food_log = FoodLog::new(&users[selected_index].name);
-                    if let Err(e) = food_log.load(&food_db) {
+                    let mut new_log = FoodLog::new(&users[index].id);
+                    if let Err(e) = new_log.load(food_db) {
+                        if schema::is_unsupported_version(&e) {
+                            println!("Error: Could not load food log: {}", e);
+                            println!("No change in selected user.");
+                            continue;
+                        }
                         println!("Warning: Could not load food log: {}", e);
                     }
+                    *selected_index = index;
+                    *food_log = new_log;
+                    users[index].recalculate_target();
                     
-                    println!("Changed to user: {}", users[selected_index].name);
+                    println!("Changed to user: {}", users[index].name);
                 } else {
                     println!("No change in selected user.");
                 }
             }
             "3" => {
-                modify_user(&mut users[selected_index]);
+                modify_user(&mut users[*selected_index])?;
                 println!("User modified successfully.");
             }
             "4" => {
-                food_database_menu(&mut food_db);
+                back_on_cancel(food_database_menu(food_db, &users[*selected_index], users))?;
             }
             "5" => {
-                food_log_menu(&mut food_log, &food_db);
+                back_on_cancel(food_log_menu(food_log, food_db, &users[*selected_index]))?;
             }
             "6" => {
-                statistics_menu(&food_log, &users[selected_index]);
+                back_on_cancel(statistics_menu(food_log, &users[*selected_index]))?;
             }
             "7" => {
-                // Save food database and log before exiting
-                if let Err(e) = food_db.save() {
-                    println!("Warning: Failed to save food database: {}", e);
+                back_on_cancel(meal_planning_menu(food_log, food_db, &users[*selected_index]))?;
+            }
+            "8" => {
+                let result = back_on_cancel(weight_journal_menu(&mut users[*selected_index]));
+                save_users(users);
+                result?;
+            }
+            "9" => {
+                let result = back_on_cancel(view_adaptive_estimate(food_log, &mut users[*selected_index]));
+                save_users(users);
+                result?;
+            }
+            "10" => {
+                let result = back_on_cancel(weight_goal_menu(food_log, &mut users[*selected_index]));
+                save_users(users);
+                result?;
+            }
+            "11" => return Ok(()),
+            _ => println!("Invalid option. Please try again."),
+        }
+    }
+}
+
+// Typing the cancel word backs out to the menu it was typed under; other
+// input errors are passed on
+fn back_on_cancel(result: Result<(), InputError>) -> Result<(), InputError> {
+    match result {
+        Err(InputError::Cancelled) => Ok(()),
+        result => result,
+    }
+}
+
+fn view_adaptive_estimate(food_log: &FoodLog, user_profile: &mut UserProfile) -> Result<(), InputError> {
+    let today = chrono::Local::now().format("%Y-%m-%d").to_string();
+    let estimate = match estimate_tdee(food_log, user_profile, &today, tdee::DEFAULT_WINDOW_DAYS) {
+        Ok(Some(estimate)) => estimate,
+        Ok(None) => {
+            println!("Not enough data for an estimate yet. Log everything you eat on at least 7 of the last {} days",
+                tdee::DEFAULT_WINDOW_DAYS);
+            println!("and record your weight at least twice, a week or more apart.");
+            return Ok(());
+        }
+        Err(e) => {
+            println!("Error estimating maintenance calories: {}", e);
+            return Ok(());
+        }
+    };
+
+    println!("\nAdaptive Maintenance Estimate (last {} days)", estimate.window_days);
+    println!("Average net intake: {:.0} calories over {} fully logged days", estimate.average_intake, estimate.logged_days);
+    if estimate.partial_days > 0 {
+        println!("{} day(s) with less than half the target logged were left out as incomplete.", estimate.partial_days);
+    }
+    println!("Trend weight change: {:+.2} kg per week from {} weigh-ins", estimate.weekly_change, estimate.weigh_ins);
+    println!("Estimated maintenance: {:.0} calories per day", estimate.tdee);
+    println!("Confidence: {}", estimate.confidence.name());
+
+    if let Some(formula) = TargetCalorieCalcStrategy::MIFFLIN_ST_JEOR.maintenance(&user_profile.strategy_input()) {
+        println!("For comparison, Mifflin-St Jeor gives {:.0}; your current target is {:.0}.", formula, user_profile.target_calorie);
+    }
+    if estimate.confidence == Confidence::Low {
+        println!("Confidence is low: log more days and weigh in more often for a better estimate.");
+    }
+
+    let input = input::read_line("Adopt this estimate as your maintenance calories? (y/n): ")?;
+    if input.trim().eq_ignore_ascii_case("y") {
+        user_profile.adopt_adaptive_target(estimate.tdee);
+        println!("Your daily target is now {:.0} calories (Adaptive).", user_profile.target_calorie);
+    }
+    Ok(())
+}
+
+fn weight_goal_menu(food_log: &FoodLog, user_profile: &mut UserProfile) -> Result<(), InputError> {
+    loop {
+        view_weight_goal(food_log, user_profile);
+
+        println!("\nWeight Goal");
+        println!("1. Set Goal");
+        println!("2. Clear Goal");
+        println!("3. Return to User Session");
+
+        let choice = input::read_line("Enter your choice: ")?;
+
+        match choice.trim() {
+            "1" => {
+                if let Some(goal) = read_weight_goal()? {
+                    user_profile.set_goal(Some(goal));
+                    println!("Goal set. Your daily target is now {:.0} calories.", user_profile.target_calorie);
+                    if let Some(plan) = user_profile.goal_plan()
+                        && !plan.achievable {
+                        println!("Warning: this goal cannot be met safely.");
+                    }
                 }
-                if let Err(e) = food_log.save() {
-                    println!("Warning: Failed to save food log: {}", e);
+            }
+            "2" => {
+                user_profile.set_goal(None);
+                println!("Goal cleared. Your daily target is back to maintenance: {:.0} calories.", user_profile.target_calorie);
+            }
+            "3" => return Ok(()),
+            _ => println!("Invalid choice, please try again."),
+        }
+    }
+}
+
+fn read_weight_goal() -> Result<Option<WeightGoal>, InputError> {
+    let input = input::read_line("Enter goal weight (in kg): ")?;
+    let target_weight: f64 = match input.trim().parse() {
+        Ok(weight) if weight > 0.0 => weight,
+        _ => {
+            println!("Invalid weight.");
+            return Ok(None);
+        }
+    };
+
+    println!("How do you want to reach it?");
+    println!("1. At a weekly rate");
+    println!("2. By a target date");
+    let input = input::read_line("Enter your choice: ")?;
+    let pace = match input.trim() {
+        "1" => {
+            let input = input::read_line("Enter rate (kg per week, e.g. 0.5): ")?;
+            match input.trim().parse::<f64>() {
+                Ok(rate) if rate > 0.0 => GoalPace::WeeklyRate(rate),
+                _ => {
+                    println!("Invalid rate.");
+                    return Ok(None);
                 }
-                println!("Exiting user session.");
-                return;
             }
-            _ => println!("Invalid option. Please try again."),
         }
+        "2" => {
+            let input = input::read_line("Enter target date (YYYY-MM-DD): ")?;
+            let date = input.trim();
+            if chrono::NaiveDate::parse_from_str(date, "%Y-%m-%d").is_err() {
+                println!("Invalid date format. Use YYYY-MM-DD.");
+                return Ok(None);
+            }
+            GoalPace::TargetDate(date.to_string())
+        }
+        _ => {
+            println!("Invalid choice.");
+            return Ok(None);
+        }
+    };
+
+    Ok(Some(WeightGoal { target_weight, pace }))
+}
+
+fn view_weight_goal(food_log: &FoodLog, user_profile: &UserProfile) {
+    let Some(goal) = &user_profile.goal else {
+        println!("\nNo weight goal set. Daily target (maintenance): {:.0} calories", user_profile.target_calorie);
+        return;
+    };
+
+    println!("\nGoal: {} (current trend weight {:.1} kg)", goal.describe(), user_profile.weight);
+    match user_profile.goal_plan() {
+        Some(plan) => {
+            if !plan.achievable {
+                println!("Warning: this goal cannot be met safely.");
+            }
+            println!("Maintenance: {:.0} calories", plan.maintenance);
+            println!("Daily target: {:.0} calories ({:+.0} per day, {:+.2} kg per week)",
+                plan.target, plan.target - plan.maintenance, plan.weekly_rate);
+            for note in &plan.notes {
+                println!("Note: {}", note);
+            }
+        }
+        None => println!("Daily target: {:.0} calories ({} strategy; not adjusted for the goal)",
+            user_profile.target_calorie, user_profile.target_calorie_calc_strategy.display_name()),
+    }
+
+    let today = chrono::Local::now().format("%Y-%m-%d").to_string();
+    match project_goal(food_log, user_profile, &today, tdee::DEFAULT_WINDOW_DAYS) {
+        Ok(Some(GoalProjection::Reached)) => println!("You have reached your goal weight."),
+        Ok(Some(GoalProjection::OnTrack { date, weekly_rate })) =>
+            println!("At your logged intake ({:+.2} kg per week) you will reach your goal around {}.", weekly_rate, date),
+        Ok(Some(GoalProjection::NotProgressing { weekly_rate })) =>
+            println!("At your logged intake ({:+.2} kg per week) you are not moving towards your goal.", weekly_rate),
+        Ok(None) => println!("Log some food to see when you will reach your goal."),
+        Err(e) => println!("Error projecting goal date: {}", e),
+    }
+}
+
+fn weight_journal_menu(user_profile: &mut UserProfile) -> Result<(), InputError> {
+    loop {
+        println!("\nWeight Journal - Trend weight: {:.1} kg, daily target: {:.0} calories",
+            user_profile.weight, user_profile.target_calorie);
+        println!("1. Record Weight");
+        println!("2. View Journal and Trend");
+        println!("3. Remove a Weigh-in");
+        println!("4. Return to User Session");
+
+        let choice = input::read_line("Enter your choice: ")?;
+
+        match choice.trim() {
+            "1" => {
+                let today = chrono::Local::now().format("%Y-%m-%d").to_string();
+                let input = input::read_line(&format!("Enter date (YYYY-MM-DD, or press Enter for {}): ", today))?;
+                let date = if input.trim().is_empty() { today } else { input.trim().to_string() };
+
+                let input = input::read_line("Enter weight (in kg): ")?;
+                let weight: f64 = match input.trim().parse() {
+                    Ok(weight) => weight,
+                    Err(_) => {
+                        println!("Invalid weight.");
+                        continue;
+                    }
+                };
+
+                match user_profile.record_weight(&date, weight) {
+                    Ok(_) => println!("Recorded {:.1} kg on {}. Trend weight is now {:.1} kg; daily target {:.0} calories.",
+                        weight, date, user_profile.weight, user_profile.target_calorie),
+                    Err(e) => println!("Error recording weight: {}", e),
+                }
+            }
+            "2" => {
+                view_weight_journal(user_profile);
+            }
+            "3" => {
+                view_weight_journal(user_profile);
+                if user_profile.weight_journal.is_empty() {
+                    continue;
+                }
+                let date = input::read_line("Enter the date of the weigh-in to remove (YYYY-MM-DD): ")?;
+                match user_profile.remove_weight(date.trim()) {
+                    Ok(_) => println!("Weigh-in removed. Trend weight is now {:.1} kg.", user_profile.weight),
+                    Err(e) => println!("Error removing weigh-in: {}", e),
+                }
+            }
+            "4" => return Ok(()),
+            _ => println!("Invalid choice, please try again."),
+        }
+    }
+}
+
+fn view_weight_journal(user_profile: &UserProfile) {
+    let journal = &user_profile.weight_journal;
+    if journal.is_empty() {
+        println!("No weigh-ins recorded.");
+        return;
+    }
+
+    println!("\n{:<12} {:>10} {:>10}", "Date", "Weight", "Trend");
+    println!("----------------------------------");
+    for (date, weight, trend) in journal.trend() {
+        println!("{:<12} {:>10.1} {:>10.1}", date, weight, trend);
+    }
+    println!("----------------------------------");
+
+    match journal.weekly_change() {
+        Some(change) => println!("Weekly change in trend: {:+.2} kg", change),
+        None => println!("Weekly change needs at least a week of weigh-ins."),
     }
 }
 
-fn food_database_menu(food_db: &mut FoodDatabase) {
+fn food_database_menu(food_db: &mut FoodDatabase, user_profile: &UserProfile, users: &[UserProfile]) -> Result<(), InputError> {
 
     loop {
-        let mut choice = String::new();
         println!("Food Database Menu:");
         println!("1. Add a Basic Food Item");
         println!("2. Add a Composite Food Item");
         println!("3. Search Foods");
         println!("4. Add Food from Website");
-        println!("5. Return to Main Menu");
+        println!("5. Find Where a Food Is Used");
+        println!("6. Return to Main Menu");
         println!();
-        println!("Enter your choice: ");
-        std::io::stdin().read_line(&mut choice).unwrap();
+        let choice = input::read_line("Enter your choice: ")?;
         match choice.trim() {
             "1" => {
                 // Get food details
-                let mut name = String::new();
-                println!("Enter the name of the food item: ");
-                std::io::stdin().read_line(&mut name).unwrap();
-                name = name.trim().to_string();
+                let name = input::read_line("Enter the name of the food item: ")?;
                 
                 // Get keywords
-                let mut keywords_input = String::new();
-                println!("Enter keywords separated by commas: ");
-                std::io::stdin().read_line(&mut keywords_input).unwrap();
+                let keywords_input = input::read_line("Enter keywords separated by commas: ")?;
                 let keywords: Vec<String> = keywords_input
                     .trim()
                     .split(',')
@@ -190,9 +542,7 @@ fn food_database_menu(food_db: &mut FoodDatabase) {
                     .collect();
                 
                 // Get calories
-                let mut calories = String::new();
-                println!("Enter the calories per serving: ");
-                std::io::stdin().read_line(&mut calories).unwrap();
+                let calories = input::read_line("Enter the calories per serving: ")?;
                 let calories: f64 = match calories.trim().parse() {
                     Ok(num) => num,
                     Err(_) => {
@@ -201,23 +551,41 @@ fn food_database_menu(food_db: &mut FoodDatabase) {
                     }
                 };
                 
+                let dietary = match read_dietary_info() {
+                    Ok(dietary) => dietary,
+                    Err(InputError::Cancelled) => {
+                        println!("Food not added.");
+                        continue;
+                    }
+                    Err(e) => return Err(e),
+                };
+                let price = read_price()?;
+                let glycemic = read_glycemic_data()?;
+                let macros = read_macros(calories)?;
+                
+                let food = BasicFood {
+                    identifier: name.clone(),
+                    keywords,
+                    calories_per_serving: calories,
+                    dietary,
+                    price,
+                    glycemic_index: glycemic.map(|(gi, _)| gi),
+                    available_carbs: glycemic.map(|(_, carbs)| carbs),
+                    macros,
+                };
+                
                 // Add to database
-                match food_db.add_basic_food(&name, keywords, calories) {
+                match food_db.add_basic_food(food) {
                     Ok(_) => println!("Basic food '{}' added successfully.", name),
                     Err(e) => println!("Failed to add basic food: {}", e),
                 }
             }
             "2" => {
                 // Get composite food details
-                let mut name = String::new();
-                println!("Enter the name of the composite food item: ");
-                std::io::stdin().read_line(&mut name).unwrap();
-                name = name.trim().to_string();
+                let name = input::read_line("Enter the name of the composite food item: ")?;
                 
                 // Get keywords
-                let mut keywords_input = String::new();
-                println!("Enter keywords separated by commas: ");
-                std::io::stdin().read_line(&mut keywords_input).unwrap();
+                let keywords_input = input::read_line("Enter keywords separated by commas: ")?;
                 let keywords: Vec<String> = keywords_input
                     .trim()
                     .split(',')
@@ -227,9 +595,7 @@ fn food_database_menu(food_db: &mut FoodDatabase) {
                 // Add components
                 let mut components: Vec<(String, f64)> = Vec::new();
                 loop {
-                    let mut component_name = String::new();
-                    println!("Enter the name of the food component (or 'done' to finish): ");
-                    std::io::stdin().read_line(&mut component_name).unwrap();
+                    let component_name = input::read_line("Enter the name of the food component (or 'done' to finish): ")?;
                     if component_name.trim() == "done" {
                         break;
                     }
@@ -246,9 +612,7 @@ fn food_database_menu(food_db: &mut FoodDatabase) {
                         println!("{}. {} ({} calories)", i+1, name, calories);
                     }
                     
-                    println!("Enter the index of the food item you want to use: ");
-                    let mut index = String::new();
-                    std::io::stdin().read_line(&mut index).unwrap();
+                    let index = input::read_line("Enter the index of the food item you want to use: ")?;
                     let index: usize = match index.trim().parse() {
                         Ok(num) => num,
                         Err(_) => {
@@ -264,9 +628,7 @@ fn food_database_menu(food_db: &mut FoodDatabase) {
                     
                     let selected_food = &results[index - 1];
                     
-                    let mut quantity = String::new();
-                    println!("Enter the quantity: ");
-                    std::io::stdin().read_line(&mut quantity).unwrap();
+                    let quantity = input::read_line("Enter the quantity: ")?;
                     let quantity: f64 = match quantity.trim().parse() {
                         Ok(num) => num,
                         Err(_) => {
@@ -291,41 +653,225 @@ fn food_database_menu(food_db: &mut FoodDatabase) {
             }
             "3" => {
                 // Search foods
-                let mut search_term = String::new();
-                println!("Enter search term: ");
-                std::io::stdin().read_line(&mut search_term).unwrap();
-                search_term = search_term.trim().to_string();
+                let search_term = input::read_line("Enter search term: ")?;
                 
-                let results = food_db.search_foods(&search_term);
+                // Leave out foods that conflict with the user's dietary restrictions
+                let (results, hidden) = food_db.search_foods_with_restrictions(
+                    &search_term, &user_profile.dietary_restrictions);
                 if results.is_empty() {
                     println!("No food items found matching '{}'", search_term);
                 } else {
                     println!("Found food items:");
                     for (i, (name, calories)) in results.iter().enumerate() {
-                        println!("{}. {} ({} calories)", i+1, name, calories);
+                        let mut line = format!("{}. {} ({} calories", i+1, name, calories);
+                        if let Some((cost, currency)) = food_db.get_cost_per_serving(name) {
+                            line.push_str(&format!(", {:.2} {}", cost, currency));
+                        }
+                        line.push(')');
+                        let info = food_db.get_dietary_info(name).unwrap_or_default();
+                        let dietary = info.describe();
+                        if !dietary.is_empty() {
+                            line.push_str(&format!(" [{}]", dietary));
+                        }
+                        let unknowns = user_profile.dietary_restrictions.unknowns(&info);
+                        if !unknowns.is_empty() {
+                            line.push_str(&format!(" [not known: {}]", unknowns.join(", ")));
+                        }
+                        println!("{}", line);
                     }
                 }
+                if hidden > 0 {
+                    println!("{} item(s) hidden because they conflict with your dietary restrictions.", hidden);
+                }
             }
             "4" => {
                 // Add food from website
-                add_food_from_website(food_db);
+                back_on_cancel(add_food_from_website(food_db))?;
+            }
+            "5" => {
+                // Reverse lookup before changing a food
+                back_on_cancel(show_food_usages(food_db, users))?;
             }
-            "5" => break,
+            "6" => return Ok(()),
             _ => println!("Invalid choice, please try again."),
         }
     }
 }
 
-fn add_food_from_website(food_db: &mut FoodDatabase) {
+fn show_food_usages(food_db: &FoodDatabase, users: &[UserProfile]) -> Result<(), InputError> {
+    let search_term = input::read_line("Enter food name to search: ")?;
+
+    let results = food_db.search_foods(search_term.trim());
+    if results.is_empty() {
+        println!("No food items found matching '{}'", search_term.trim());
+        return Ok(());
+    }
+
+    println!("Found food items:");
+    for (i, (name, calories)) in results.iter().enumerate() {
+        println!("{}. {} ({} calories)", i+1, name, calories);
+    }
+    let index = input::read_line("Enter the number of the food item (or 0 to cancel): ")?;
+    let (food_id, current_calories) = match index.trim().parse::<usize>() {
+        Ok(i) if i > 0 && i <= results.len() => results[i - 1],
+        _ => return Ok(()),
+    };
+
+    let usage = match food_db.find_usages(food_id, users) {
+        Ok(usage) => usage,
+        Err(e) => {
+            println!("Error looking up usages: {}", e);
+            return Ok(());
+        }
+    };
+
+    if usage.is_empty() {
+        println!("'{}' is not used by any composite food or food log.", food_id);
+        return Ok(());
+    }
+
+    let proposed = input::read_line("Enter a proposed new calories per serving to see its impact (or press Enter to skip): ")?;
+    let delta = proposed.trim().parse::<f64>().ok().map(|new| new - current_calories);
+
+    if !usage.composites.is_empty() {
+        println!("\nComposite foods containing '{}':", food_id);
+        for (composite, quantity) in &usage.composites {
+            match delta {
+                Some(delta) => println!("- {} (x{:.2}): {:+.1} calories per serving", composite, quantity, quantity * delta),
+                None => println!("- {} (x{:.2})", composite, quantity),
+            }
+        }
+    }
+
+    if !usage.log_entries.is_empty() {
+        println!("\nFood logs referencing '{}':", food_id);
+        println!("{:<20} {:<12} {:>10} {:>12}", "User", "Date", "Servings", "Impact");
+        println!("--------------------------------------------------------");
+        for (user, date, servings) in &usage.log_entries {
+            match delta {
+                Some(delta) => println!("{:<20} {:<12} {:>10.1} {:>+12.1}", user, date, servings, servings * delta),
+                None => println!("{:<20} {:<12} {:>10.1} {:>12}", user, date, servings, "-"),
+            }
+        }
+        if delta.is_some() {
+            println!("Logged entries keep the calories recorded when they were logged; the impact shows the difference if they were recalculated.");
+        }
+    }
+    Ok(())
+}
+
+// Ask for the dietary attributes of a new food
+fn read_dietary_info() -> Result<DietaryInfo, InputError> {
+    let names: Vec<&str> = Allergen::ALL.iter().map(|a| a.name()).collect();
+    let input = input::read_line(&format!(
+        "Enter allergens separated by commas ({}), or press Enter for none: ", names.join(", ")))?;
+
+    let (mut allergens, unknown) = Allergen::parse_list(&input);
+    for name in unknown {
+        println!("Unknown allergen '{}' ignored.", name);
+    }
+
+    let vegan = input::read_optional_yes_no("Is it vegan?")?;
+    let vegetarian = input::read_optional_yes_no("Is it vegetarian?")?;
+    let halal = input::read_optional_yes_no("Is it halal?")?;
+
+    // Gluten is recorded as an allergen, so only ask when it was not listed
+    let mut gluten_free = None;
+    if !allergens.contains(&Allergen::Gluten) {
+        match input::read_optional_yes_no("Is it gluten-free?")? {
+            Some(true) => gluten_free = Some(true),
+            Some(false) => allergens.push(Allergen::Gluten),
+            None => {}
+        }
+    }
+
+    Ok(DietaryInfo { allergens, vegan, vegetarian, halal, gluten_free })
+}
+
+// Ask for an optional price per serving
+fn read_price() -> Result<Option<Price>, InputError> {
+    let input = input::read_line("Enter the price per serving (or press Enter to skip): ")?;
+    let amount: f64 = match input.trim().parse() {
+        Ok(amount) if amount >= 0.0 => amount,
+        _ => return Ok(None),
+    };
+
+    let input = input::read_line("Enter the currency (e.g. USD): ")?;
+    let currency = input.trim().to_uppercase();
+    if currency.is_empty() {
+        println!("No currency given, price not recorded.");
+        return Ok(None);
+    }
+
+    let today = chrono::Local::now().format("%Y-%m-%d").to_string();
+    let input = input::read_line("Enter the date of this price (YYYY-MM-DD, or press Enter for today): ")?;
+    let date = match input.trim() {
+        "" => today,
+        date if chrono::NaiveDate::parse_from_str(date, "%Y-%m-%d").is_ok() => date.to_string(),
+        _ => {
+            println!("Invalid date, using today.");
+            today
+        }
+    };
+
+    Ok(Some(Price { amount, currency, date }))
+}
+
+// Ask for an optional glycemic index and available carbohydrate per serving
+fn read_glycemic_data() -> Result<Option<(f64, f64)>, InputError> {
+    let input = input::read_line("Enter the glycemic index (0-100, or press Enter to skip): ")?;
+    let glycemic_index: f64 = match input.trim().parse() {
+        Ok(gi) if (0.0..=100.0).contains(&gi) => gi,
+        Ok(_) => {
+            println!("Glycemic index must be between 0 and 100, not recorded.");
+            return Ok(None);
+        }
+        Err(_) => return Ok(None),
+    };
+
+    let input = input::read_line("Enter the available carbohydrate per serving (grams): ")?;
+    Ok(match input.trim().parse::<f64>() {
+        Ok(carbs) if carbs >= 0.0 => Some((glycemic_index, carbs)),
+        _ => {
+            println!("Invalid carbohydrate amount, glycemic data not recorded.");
+            None
+        }
+    })
+}
+
+// Ask for optional protein, carbs and fat per serving
+fn read_macros(calories_per_serving: f64) -> Result<Option<Macros>, InputError> {
+    let input = input::read_line("Enter protein, carbs and fat per serving in grams as 'protein,carbs,fat' (or press Enter to skip): ")?;
+    if input.trim().is_empty() {
+        return Ok(None);
+    }
+
+    let parts: Vec<Option<f64>> = input.trim().split(',').map(|p| p.trim().parse::<f64>().ok()).collect();
+    Ok(match parts.as_slice() {
+        [Some(protein), Some(carbs), Some(fat)] if [protein, carbs, fat].iter().all(|v| **v >= 0.0) => {
+            let macros = Macros { protein: *protein, carbs: *carbs, fat: *fat };
+            // Fibre and rounding on labels account for small differences
+            if (macros.calories() - calories_per_serving).abs() > calories_per_serving * 0.2 + 20.0 {
+                println!("Note: these macros add up to {:.0} calories, but the food has {:.0} per serving.",
+                    macros.calories(), calories_per_serving);
+            }
+            Some(macros)
+        }
+        _ => {
+            println!("Invalid macros, not recorded.");
+            None
+        }
+    })
+}
+
+fn add_food_from_website(food_db: &mut FoodDatabase) -> Result<(), InputError> {
     // Get website URL from user
-    let mut url = String::new();
-    println!("Enter the website URL for the food information: ");
-    std::io::stdin().read_line(&mut url).expect("Failed to read input");
+    let url = input::read_line("Enter the website URL for the food information: ")?;
     let mut url = url.trim().to_string();
     
     if url.is_empty() {
         println!("URL cannot be empty. Returning to menu.");
-        return;
+        return Ok(());
     }
     
     // Add https:// prefix if not present
@@ -342,7 +888,7 @@ fn add_food_from_website(food_db: &mut FoodDatabase) {
         Ok(rt) => rt,
         Err(e) => {
             println!("Failed to create runtime: {}", e);
-            return;
+            return Ok(());
         }
     };
     
@@ -360,9 +906,10 @@ fn add_food_from_website(food_db: &mut FoodDatabase) {
             println!("Try again with a different URL or check if Ollama is running.");
         }
     }
+    Ok(())
 }
 
-fn food_log_menu(food_log: &mut FoodLog, food_db: &FoodDatabase) {
+fn food_log_menu(food_log: &mut FoodLog, food_db: &FoodDatabase, user_profile: &UserProfile) -> Result<(), InputError> {
     loop {
         println!("\nFood Log Menu - Current Date: {}", food_log.current_date);
         println!("1. Add Food to Today's Log");
@@ -370,104 +917,796 @@ fn food_log_menu(food_log: &mut FoodLog, food_db: &FoodDatabase) {
         println!("3. Change Current Date");
         println!("4. View Log for Specific Date");
         println!("5. Remove Food Entry");
-        println!("6. Undo Last Action");
-        println!("7. Return to User Session");
+        println!("6. Edit Food Entry");
+        println!("7. Undo Last Action");
+        println!("8. Redo Last Undone Action");
+        println!("9. View History");
+        println!("10. Copy Day or Meal");
+        println!("11. Meal Templates");
+        println!("12. Confirm Planned Entries");
+        println!("13. Recurring Meals");
+        println!("14. Exercise Log");
+        println!("15. Return to User Session");
 
-        let mut choice = String::new();
-        println!("Enter your choice: ");
-        io::stdin().read_line(&mut choice).expect("Failed to read input");
+        let choice = input::read_line("Enter your choice: ")?;
 
         match choice.trim() {
             "1" => {
                 // Add food to log
-                add_food_to_log(food_log, food_db);
+                back_on_cancel(add_food_to_log(food_log, food_db, user_profile))?;
             }
             "2" => {
                 // View current log
-                view_daily_log(food_log);
+                view_daily_log(food_log, user_profile);
             }
             "3" => {
                 // Change date
-                change_log_date(food_log);
+                back_on_cancel(change_log_date(food_log, food_db))?;
             }
             "4" => {
                 // View log for specific date
-                view_log_for_specific_date(food_log);
+                back_on_cancel(view_log_for_specific_date(food_log, user_profile))?;
             }
             "5" => {
                 // Remove food entry
-                remove_food_from_log(food_log);
+                back_on_cancel(remove_food_from_log(food_log, user_profile))?;
             }
             "6" => {
+                // Change servings, meal or date of an entry
+                back_on_cancel(edit_food_in_log(food_log, user_profile))?;
+            }
+            "7" => {
                 // Undo last action
                 match food_log.undo() {
-                    Ok(_) => println!("Last action undone."),
+                    Ok(description) => println!("Undone: {}", description),
                     Err(e) => println!("Could not undo: {}", e),
                 }
             }
-            "7" => break,
+            "8" => {
+                // Redo last undone action
+                match food_log.redo() {
+                    Ok(description) => println!("Redone: {}", description),
+                    Err(e) => println!("Could not redo: {}", e),
+                }
+            }
+            "9" => {
+                // View history and optionally jump back
+                back_on_cancel(view_history(food_log))?;
+            }
+            "10" => {
+                // Copy a day or meal onto other dates
+                back_on_cancel(copy_day_or_meal(food_log))?;
+            }
+            "11" => {
+                // Save and apply meal templates
+                back_on_cancel(meal_templates_menu(food_log, food_db, user_profile))?;
+            }
+            "12" => {
+                // Mark planned entries as eaten
+                back_on_cancel(confirm_planned_entries(food_log, user_profile))?;
+            }
+            "13" => {
+                // Manage recurring meals
+                back_on_cancel(recurring_meals_menu(food_log))?;
+            }
+            "14" => {
+                // Log exercise for the current date
+                back_on_cancel(exercise_menu(food_log, user_profile))?;
+            }
+            "15" => return Ok(()),
             _ => println!("Invalid choice, please try again."),
         }
     }
 }
 
-fn add_food_to_log(food_log: &mut FoodLog, food_db: &FoodDatabase) {
-    // Search for food
-    let mut search_term = String::new();
-    println!("Enter food name to search: ");
-    io::stdin().read_line(&mut search_term).expect("Failed to read input");
-    search_term = search_term.trim().to_string();
-    
-    let results = food_db.search_foods(&search_term);
-    if results.is_empty() {
-        println!("No food items found matching '{}'", search_term);
-        return;
-    }
-    
-    // Display results
-    println!("Found food items:");
-    for (i, (name, calories)) in results.iter().enumerate() {
-        println!("{}. {} ({} calories per serving)", i+1, name, calories);
-    }
-    
-    // Select food
-    println!("Enter the number of the food item (or 0 to cancel): ");
-    let mut index = String::new();
-    io::stdin().read_line(&mut index).expect("Failed to read input");
-    let index: usize = match index.trim().parse() {
-        Ok(num) => num,
-        Err(_) => {
-            println!("Invalid input. Returning to menu.");
-            return;
+fn copy_day_or_meal(food_log: &mut FoodLog) -> Result<(), InputError> {
+    let input = input::read_line(&format!("Enter date to copy from (YYYY-MM-DD, or press Enter for {}): ", food_log.current_date))?;
+    let from_date = if input.trim().is_empty() {
+        food_log.current_date.clone()
+    } else {
+        input.trim().to_string()
+    };
+
+    println!("Copy:");
+    println!("1. The whole day");
+    println!("2. One meal");
+    let input = input::read_line("Enter your choice: ")?;
+    let meal = match input.trim() {
+        "1" => None,
+        "2" => match read_meal()? {
+            Some(meal) => Some(meal),
+            None => {
+                println!("Invalid meal. Returning to menu.");
+                return Ok(());
+            }
+        },
+        _ => {
+            println!("Invalid choice. Returning to menu.");
+            return Ok(());
         }
     };
-    
-    if index == 0 {
-        return;
-    } else if index > results.len() {
-        println!("Invalid selection. Returning to menu.");
-        return;
+
+    let input = input::read_line("Enter first date to copy to (YYYY-MM-DD): ")?;
+    let start_date = input.trim().to_string();
+
+    let input = input::read_line("Enter last date to copy to (YYYY-MM-DD, or press Enter for a single day): ")?;
+    let end_date = if input.trim().is_empty() {
+        start_date.clone()
+    } else {
+        input.trim().to_string()
+    };
+
+    match food_log.copy_entries(&from_date, meal.as_ref(), &start_date, &end_date) {
+        Ok(count) => println!("Copied {} entries.", count),
+        Err(e) => println!("Error copying entries: {}", e),
+    }
+    Ok(())
+}
+
+fn meal_templates_menu(food_log: &mut FoodLog, food_db: &FoodDatabase, user_profile: &UserProfile) -> Result<(), InputError> {
+    loop {
+        println!("\nMeal Templates - Current Date: {}", food_log.current_date);
+        println!("1. List Templates");
+        println!("2. Save a Meal from the Current Date as a Template");
+        println!("3. Apply a Template to the Current Date");
+        println!("4. Delete a Template");
+        println!("5. Return to Food Log Menu");
+
+        let choice = input::read_line("Enter your choice: ")?;
+
+        match choice.trim() {
+            "1" => {
+                list_templates(food_log);
+            }
+            "2" => {
+                let Some(meal) = read_meal()? else {
+                    println!("Invalid meal.");
+                    continue;
+                };
+
+                let name = input::read_line("Enter a name for the template: ")?;
+                let name = name.trim();
+                if name.is_empty() {
+                    println!("Template name cannot be empty.");
+                    continue;
+                }
+
+                let date = food_log.current_date.clone();
+                match food_log.save_template(name, &date, &meal) {
+                    Ok(count) => println!("Saved template '{}' with {} foods.", name, count),
+                    Err(e) => println!("Error saving template: {}", e),
+                }
+            }
+            "3" => {
+                let Some(name) = select_template(food_log, "apply")? else {
+                    continue;
+                };
+                match food_log.apply_template(&name, food_db) {
+                    Ok(ids) => {
+                        println!("Logged {} foods from '{}'.", ids.len(), name);
+                        view_daily_log(food_log, user_profile);
+                    }
+                    Err(e) => println!("Error applying template: {}", e),
+                }
+            }
+            "4" => {
+                let Some(name) = select_template(food_log, "delete")? else {
+                    continue;
+                };
+                match food_log.delete_template(&name) {
+                    Ok(_) => println!("Deleted template '{}'.", name),
+                    Err(e) => println!("Error deleting template: {}", e),
+                }
+            }
+            "5" => return Ok(()),
+            _ => println!("Invalid choice, please try again."),
+        }
+    }
+}
+
+fn list_templates(food_log: &FoodLog) {
+    let templates = food_log.get_templates();
+    if templates.is_empty() {
+        println!("No templates saved.");
+        return;
+    }
+
+    for (i, template) in templates.iter().enumerate() {
+        println!("{}. {} ({})", i + 1, template.name, template.meal.name());
+        for (food_id, servings) in &template.items {
+            println!("     - {} (x{:.1} servings)", food_id, servings);
+        }
+    }
+}
+
+fn select_template(food_log: &FoodLog, action: &str) -> Result<Option<String>, InputError> {
+    list_templates(food_log);
+    let templates = food_log.get_templates();
+    if templates.is_empty() {
+        return Ok(None);
+    }
+
+    let input = input::read_line(&format!("Enter the number of the template to {} (or 0 to cancel): ", action))?;
+    Ok(match input.trim().parse::<usize>() {
+        Ok(n) if n >= 1 && n <= templates.len() => Some(templates[n - 1].name.clone()),
+        Ok(0) => None,
+        _ => {
+            println!("Invalid selection.");
+            None
+        }
+    })
+}
+
+fn exercise_menu(food_log: &mut FoodLog, user_profile: &UserProfile) -> Result<(), InputError> {
+    loop {
+        println!("\nExercise Log - Current Date: {}", food_log.current_date);
+        println!("1. Log Exercise");
+        println!("2. View Exercise");
+        println!("3. Remove Exercise");
+        println!("4. Return to Food Log Menu");
+
+        let choice = input::read_line("Enter your choice: ")?;
+
+        match choice.trim() {
+            "1" => {
+                back_on_cancel(log_exercise(food_log, user_profile))?;
+            }
+            "2" => {
+                list_exercise(food_log);
+            }
+            "3" => {
+                let count = list_exercise(food_log);
+                if count == 0 {
+                    continue;
+                }
+                let input = input::read_line("Enter the number of the exercise to remove (or 0 to cancel): ")?;
+                match input.trim().parse::<usize>() {
+                    Ok(0) => {}
+                    Ok(n) if n <= count => {
+                        let id = food_log.get_current_log().map(|log| log.exercises[n - 1].id);
+                        if let Some(id) = id {
+                            match food_log.remove_exercise(id) {
+                                Ok(_) => println!("Exercise removed."),
+                                Err(e) => println!("Error removing exercise: {}", e),
+                            }
+                        }
+                    }
+                    _ => println!("Invalid selection."),
+                }
+            }
+            "4" => return Ok(()),
+            _ => println!("Invalid choice, please try again."),
+        }
+    }
+}
+
+// Print the current date's exercise, numbered. Returns how many there are.
+fn list_exercise(food_log: &FoodLog) -> usize {
+    let exercises = food_log.get_current_log().map(|log| log.exercises.as_slice()).unwrap_or_default();
+    if exercises.is_empty() {
+        println!("No exercise logged on {}.", food_log.current_date);
+        return 0;
+    }
+
+    for (i, exercise) in exercises.iter().enumerate() {
+        let estimated = if exercise.estimated { " (estimated)" } else { "" };
+        println!("{}. {} for {:.0} min - {:.1} calories burned{}",
+            i + 1, exercise.activity, exercise.duration_minutes, exercise.calories_burned, estimated);
+    }
+    exercises.len()
+}
+
+fn log_exercise(food_log: &mut FoodLog, user_profile: &UserProfile) -> Result<(), InputError> {
+    println!("Select activity:");
+    for (i, (name, met)) in ACTIVITIES.iter().enumerate() {
+        println!("{}. {} (MET {:.1})", i + 1, name, met);
+    }
+    println!("{}. Other (enter a name)", ACTIVITIES.len() + 1);
+
+    let input = input::read_line("Enter your choice: ")?;
+    let activity = match input.trim().parse::<usize>() {
+        Ok(n) if n >= 1 && n <= ACTIVITIES.len() => ACTIVITIES[n - 1].0.to_string(),
+        Ok(n) if n == ACTIVITIES.len() + 1 => {
+            let input = input::read_line("Enter activity name: ")?;
+            if input.trim().is_empty() {
+                println!("Activity name cannot be empty.");
+                return Ok(());
+            }
+            input.trim().to_string()
+        }
+        _ => {
+            println!("Invalid selection.");
+            return Ok(());
+        }
+    };
+
+    let input = input::read_line("Enter duration in minutes: ")?;
+    let duration: f64 = match input.trim().parse() {
+        Ok(minutes) if minutes > 0.0 => minutes,
+        _ => {
+            println!("Invalid duration.");
+            return Ok(());
+        }
+    };
+
+    // Offer an estimate when we know the activity's MET value
+    let estimate = met_for(&activity).map(|met| estimate_calories_burned(met, user_profile.weight, duration));
+    let input = match estimate {
+        Some(estimate) => input::read_line(&format!("Enter calories burned (or press Enter to use the estimate of {:.0}): ", estimate))?,
+        None => input::read_line("Enter calories burned: ")?,
+    };
+    let (calories, estimated) = match (input.trim(), estimate) {
+        ("", Some(estimate)) => (estimate, true),
+        (value, _) => match value.parse::<f64>() {
+            Ok(calories) if calories >= 0.0 => (calories, false),
+            _ => {
+                println!("Invalid calories.");
+                return Ok(());
+            }
+        },
+    };
+
+    match food_log.add_exercise(&activity, duration, calories, estimated) {
+        Ok(_) => println!("Logged {} for {:.0} min ({:.0} calories burned).", activity, duration, calories),
+        Err(e) => println!("Error logging exercise: {}", e),
+    }
+    Ok(())
+}
+
+fn confirm_planned_entries(food_log: &mut FoodLog, user_profile: &UserProfile) -> Result<(), InputError> {
+    let planned = food_log.get_current_log()
+        .map_or(0, |log| log.entries.iter().filter(|e| !e.is_eaten()).count());
+    if planned == 0 {
+        println!("No planned entries on {}.", food_log.current_date);
+        return Ok(());
+    }
+
+    println!("1. Confirm all {} planned entries", planned);
+    println!("2. Confirm a single entry");
+    let input = input::read_line("Enter your choice: ")?;
+
+    match input.trim() {
+        "1" => match food_log.confirm_all_planned() {
+            Ok(count) => println!("Confirmed {} entries.", count),
+            Err(e) => println!("Error confirming entries: {}", e),
+        },
+        "2" => {
+            if let Some(entry_id) = select_log_entry(food_log, user_profile, "confirm")? {
+                match food_log.confirm_entry(entry_id) {
+                    Ok(_) => println!("Entry confirmed."),
+                    Err(e) => println!("Error confirming entry: {}", e),
+                }
+            }
+        }
+        _ => println!("Invalid choice."),
+    }
+    Ok(())
+}
+
+fn recurring_meals_menu(food_log: &mut FoodLog) -> Result<(), InputError> {
+    loop {
+        println!("\nRecurring Meals");
+        println!("1. List Recurring Meals");
+        println!("2. Add a Recurring Meal from a Template");
+        println!("3. Pause or Resume a Recurring Meal");
+        println!("4. Delete a Recurring Meal");
+        println!("5. Return to Food Log Menu");
+
+        let choice = input::read_line("Enter your choice: ")?;
+
+        match choice.trim() {
+            "1" => {
+                list_recurring_meals(food_log);
+            }
+            "2" => {
+                let Some(template) = select_template(food_log, "repeat")? else {
+                    continue;
+                };
+
+                let input = input::read_line("Enter the days it repeats on (daily, weekdays, weekends, or e.g. Mon,Wed,Fri): ")?;
+                let weekdays = match RecurrenceRule::parse_weekdays(&input) {
+                    Ok(days) => days,
+                    Err(e) => {
+                        println!("{}", e);
+                        continue;
+                    }
+                };
+
+                let input = input::read_line(&format!("Enter a name for the recurring meal (or press Enter for '{}'): ", template))?;
+                let name = if input.trim().is_empty() { template.clone() } else { input.trim().to_string() };
+
+                match food_log.add_recurrence_rule(&name, &template, weekdays) {
+                    Ok(_) => println!("Recurring meal '{}' added. It will be planned from today on.", name),
+                    Err(e) => println!("Error adding recurring meal: {}", e),
+                }
+            }
+            "3" => {
+                let Some(rule) = select_recurring_meal(food_log, "pause or resume")? else {
+                    continue;
+                };
+                let paused = !rule.paused;
+                match food_log.set_recurrence_rule_paused(&rule.name, paused) {
+                    Ok(_) if paused => println!("Paused '{}'.", rule.name),
+                    Ok(_) => println!("Resumed '{}'.", rule.name),
+                    Err(e) => println!("Error: {}", e),
+                }
+            }
+            "4" => {
+                let Some(rule) = select_recurring_meal(food_log, "delete")? else {
+                    continue;
+                };
+                match food_log.delete_recurrence_rule(&rule.name) {
+                    Ok(_) => println!("Deleted '{}'. Entries it already planned were kept.", rule.name),
+                    Err(e) => println!("Error deleting recurring meal: {}", e),
+                }
+            }
+            "5" => return Ok(()),
+            _ => println!("Invalid choice, please try again."),
+        }
+    }
+}
+
+fn list_recurring_meals(food_log: &FoodLog) {
+    let rules = food_log.get_recurrence_rules();
+    if rules.is_empty() {
+        println!("No recurring meals.");
+        return;
+    }
+
+    for (i, rule) in rules.iter().enumerate() {
+        let status = if rule.paused { " [paused]" } else { "" };
+        println!("{}. {} - {} on {}{}", i + 1, rule.name, rule.meal.name(), rule.weekdays.join(", "), status);
+        for (food_id, servings) in &rule.items {
+            println!("     - {} (x{:.1} servings)", food_id, servings);
+        }
+    }
+}
+
+fn select_recurring_meal(food_log: &FoodLog, action: &str) -> Result<Option<RecurrenceRule>, InputError> {
+    list_recurring_meals(food_log);
+    let rules = food_log.get_recurrence_rules();
+    if rules.is_empty() {
+        return Ok(None);
+    }
+
+    let input = input::read_line(&format!("Enter the number of the recurring meal to {} (or 0 to cancel): ", action))?;
+    Ok(match input.trim().parse::<usize>() {
+        Ok(n) if n >= 1 && n <= rules.len() => Some(rules[n - 1].clone()),
+        Ok(0) => None,
+        _ => {
+            println!("Invalid selection.");
+            None
+        }
+    })
+}
+
+fn view_history(food_log: &mut FoodLog) -> Result<(), InputError> {
+    // Number of actions shown per page
+    const PAGE_SIZE: usize = 20;
+
+    let history = food_log.get_history();
+    if history.is_empty() {
+        println!("No actions in history.");
+        return Ok(());
+    }
+    let total = history.len();
+    if food_log.can_redo() {
+        println!("(Some undone actions can be redone with Redo.)");
+    }
+
+    // Newest first; number 1 is the most recent action
+    println!("\nRecent actions (newest first):");
+    println!("{:<4} {:<20} Action", "#", "Time");
+    println!("{:-<60}", "");
+    let mut shown = 0;
+    let input = loop {
+        for (i, record) in history.iter().rev().enumerate().skip(shown).take(PAGE_SIZE) {
+            println!("{:<4} {:<20} {}", i + 1, record.timestamp, record.description);
+        }
+        shown = total.min(shown + PAGE_SIZE);
+        let prompt = if shown < total {
+            println!("... and {} older action(s)", total - shown);
+            "\nEnter an action number to undo back to and including it, 'm' for more (or press Enter to return): "
+        } else {
+            "\nEnter an action number to undo back to and including it (or press Enter to return): "
+        };
+        let input = input::read_line(prompt)?;
+        let input = input.trim().to_string();
+        if shown < total && input.eq_ignore_ascii_case("m") {
+            continue;
+        }
+        break input;
+    };
+    if input.is_empty() {
+        return Ok(());
+    }
+
+    // Any action in the history can be chosen, not just those on screen
+    match input.parse::<usize>() {
+        Ok(n) if n >= 1 && n <= total => {
+            let keep = total - n;
+            match food_log.undo_to(keep) {
+                Ok(undone) => println!("Undid {} action(s).", undone),
+                Err(e) => println!("Could not undo: {}", e),
+            }
+        }
+        _ => println!("Invalid action number."),
+    }
+    Ok(())
+}
+
+// Search the database and let the user pick a food, warning about dietary conflicts
+fn search_and_select_food<'a>(food_db: &'a FoodDatabase, user_profile: &UserProfile) -> Result<Option<&'a str>, InputError> {
+    // Search for food
+    let search_term = input::read_line("Enter food name to search: ")?;
+    
+    let results = food_db.search_foods(&search_term);
+    if results.is_empty() {
+        println!("No food items found matching '{}'", search_term);
+        return Ok(None);
+    }
+    
+    // Display results, flagging anything that conflicts with the user's restrictions
+    println!("Found food items:");
+    for (i, (name, calories)) in results.iter().enumerate() {
+        let conflicts = food_db.get_dietary_info(name)
+            .map(|info| user_profile.dietary_restrictions.violations(&info))
+            .unwrap_or_default();
+        let unknown = food_db.get_dietary_info(name)
+            .is_some_and(|info| !user_profile.dietary_restrictions.unknowns(&info).is_empty());
+        if !conflicts.is_empty() {
+            println!("{}. {} ({} calories per serving) [!]", i+1, name, calories);
+        } else if unknown {
+            println!("{}. {} ({} calories per serving) [?]", i+1, name, calories);
+        } else {
+            println!("{}. {} ({} calories per serving)", i+1, name, calories);
+        }
+    }
+    
+    // Select food
+    let index = input::read_line("Enter the number of the food item (or 0 to cancel): ")?;
+    let index: usize = match index.trim().parse() {
+        Ok(num) => num,
+        Err(_) => {
+            println!("Invalid input. Returning to menu.");
+            return Ok(None);
+        }
+    };
+    
+    if index == 0 {
+        return Ok(None);
+    } else if index > results.len() {
+        println!("Invalid selection. Returning to menu.");
+        return Ok(None);
     }
     
     // Get the food
     let selected_food_id = results[index - 1].0;
+
+    if let Some(info) = food_db.get_dietary_info(selected_food_id) {
+        let conflicts = user_profile.dietary_restrictions.violations(&info);
+        if !conflicts.is_empty() {
+            println!("Warning: {} conflicts with your dietary restrictions ({}).",
+                selected_food_id, conflicts.join(", "));
+        }
+        let unknowns = user_profile.dietary_restrictions.unknowns(&info);
+        if !unknowns.is_empty() {
+            println!("Note: it is not known whether {} is {}.", selected_food_id, unknowns.join(" or "));
+        }
+    }
+    
+    Ok(Some(selected_food_id))
+}
+
+// Number of days shown when planning ahead
+const PLANNING_DAYS: i64 = 7;
+
+fn meal_planning_menu(food_log: &mut FoodLog, food_db: &FoodDatabase, user_profile: &UserProfile) -> Result<(), InputError> {
+    loop {
+        let today = chrono::Local::now().format("%Y-%m-%d").to_string();
+        println!("\nMeal Planning - Today's Target: {:.0} calories", user_profile.target_for_date(&today));
+        println!("1. View the Week Ahead");
+        println!("2. Plan a Food");
+        println!("3. Remove a Planned Entry");
+        println!("4. Planned vs Actual Report");
+        println!("5. Return to User Session");
+
+        let choice = input::read_line("Enter your choice: ")?;
+
+        match choice.trim() {
+            "1" => {
+                view_week_plan(food_log, user_profile);
+            }
+            "2" => {
+                let Some(date) = select_planning_day(food_log, user_profile)? else {
+                    continue;
+                };
+                let Some(food_id) = search_and_select_food(food_db, user_profile)? else {
+                    continue;
+                };
+
+                let servings = input::read_line("Enter number of servings: ")?;
+                let servings = match servings.trim().parse::<f64>() {
+                    Ok(num) if num > 0.0 && num.is_finite() => num,
+                    _ => {
+                        println!("Invalid servings.");
+                        continue;
+                    }
+                };
+
+                let Some(meal) = read_meal()? else {
+                    println!("Invalid meal.");
+                    continue;
+                };
+
+                match food_log.plan_food_entry(&date, food_id, servings, meal, food_db) {
+                    Ok(_) => {
+                        let planned = planned_intake(food_log, &date);
+                        let target = user_profile.target_for_date(&date);
+                        println!("Planned {} for {}. Day total: {:.0} / {:.0} calories ({:.0} remaining)",
+                            food_id, date, planned, target, target - planned);
+                    }
+                    Err(e) => println!("Error planning food: {}", e),
+                }
+            }
+            "3" => {
+                back_on_cancel(remove_planned_entry(food_log, user_profile))?;
+            }
+            "4" => {
+                back_on_cancel(view_plan_comparison(food_log, user_profile))?;
+            }
+            "5" => return Ok(()),
+            _ => println!("Invalid choice, please try again."),
+        }
+    }
+}
+
+// Calories expected on a date: what was eaten plus what is still planned
+fn planned_intake(food_log: &FoodLog, date: &str) -> f64 {
+    food_log.get_entries_for_date(date)
+        .map_or(0.0, |entries| entries.iter().map(|e| e.calories * e.servings).sum())
+}
+
+fn upcoming_days() -> Vec<String> {
+    let today = chrono::Local::now().date_naive();
+    (0..PLANNING_DAYS)
+        .map(|offset| (today + chrono::Duration::days(offset)).format("%Y-%m-%d").to_string())
+        .collect()
+}
+
+fn view_week_plan(food_log: &FoodLog, user_profile: &UserProfile) {
+    println!("\nThe Week Ahead");
+    println!("{:<4} {:<16} {:>10} {:>10} {:>10}", "#", "Date", "Planned", "Target", "Remaining");
+    println!("{:-<54}", "");
+    for (i, date) in upcoming_days().iter().enumerate() {
+        let weekday = chrono::NaiveDate::parse_from_str(date, "%Y-%m-%d")
+            .map(|d| d.format("%a").to_string())
+            .unwrap_or_default();
+        let planned = planned_intake(food_log, date);
+        let target = user_profile.target_for_date(date);
+        println!("{:<4} {:<16} {:>10.0} {:>10.0} {:>10.0}",
+            i + 1, format!("{} {}", date, weekday), planned, target, target - planned);
+    }
+}
+
+// Pick one of the upcoming days, or type any date
+fn select_planning_day(food_log: &FoodLog, user_profile: &UserProfile) -> Result<Option<String>, InputError> {
+    view_week_plan(food_log, user_profile);
+
+    let input = input::read_line("Enter a day number or a date (YYYY-MM-DD), or 0 to cancel: ")?;
+    let input = input.trim();
+
+    let days = upcoming_days();
+    Ok(match input.parse::<usize>() {
+        Ok(0) => None,
+        Ok(n) if n <= days.len() => Some(days[n - 1].clone()),
+        Ok(_) => {
+            println!("Invalid day number.");
+            None
+        }
+        Err(_) if chrono::NaiveDate::parse_from_str(input, "%Y-%m-%d").is_ok() => Some(input.to_string()),
+        Err(_) => {
+            println!("Invalid date format. Use YYYY-MM-DD.");
+            None
+        }
+    })
+}
+
+fn remove_planned_entry(food_log: &mut FoodLog, user_profile: &UserProfile) -> Result<(), InputError> {
+    let Some(date) = select_planning_day(food_log, user_profile)? else {
+        return Ok(());
+    };
+
+    let planned: Vec<(u64, String)> = food_log.get_entries_for_date(&date)
+        .map(|entries| entries.iter()
+            .filter(|e| !e.is_eaten())
+            .map(|e| (e.id, format!("{} - {} (x{:.1} servings) - {:.1} calories",
+                e.meal.name(), e.food_id, e.servings, e.calories * e.servings)))
+            .collect())
+        .unwrap_or_default();
+    if planned.is_empty() {
+        println!("Nothing planned for {}.", date);
+        return Ok(());
+    }
+
+    for (i, (_, description)) in planned.iter().enumerate() {
+        println!("{}. {}", i + 1, description);
+    }
+    let input = input::read_line("Enter the number of the entry to remove (or 0 to cancel): ")?;
+    match input.trim().parse::<usize>() {
+        Ok(0) => {}
+        Ok(n) if n <= planned.len() => match food_log.remove_entry_on_date(&date, planned[n - 1].0) {
+            Ok(_) => println!("Planned entry removed."),
+            Err(e) => println!("Error removing entry: {}", e),
+        },
+        _ => println!("Invalid selection."),
+    }
+    Ok(())
+}
+
+fn view_plan_comparison(food_log: &FoodLog, user_profile: &UserProfile) -> Result<(), InputError> {
+    let today = chrono::Local::now().date_naive();
+    let default_start = (today - chrono::Duration::days(PLANNING_DAYS - 1)).format("%Y-%m-%d").to_string();
+    let default_end = today.format("%Y-%m-%d").to_string();
+
+    let input = input::read_line(&format!("Enter start date (YYYY-MM-DD, or press Enter for {}): ", default_start))?;
+    let start_date = if input.trim().is_empty() { default_start } else { input.trim().to_string() };
+
+    let input = input::read_line(&format!("Enter end date (YYYY-MM-DD, or press Enter for {}): ", default_end))?;
+    let end_date = if input.trim().is_empty() { default_end } else { input.trim().to_string() };
+
+    let comparison = match get_plan_comparison(food_log, &start_date, &end_date, user_profile) {
+        Ok(comparison) => comparison,
+        Err(e) => {
+            println!("Error: {}", e);
+            return Ok(());
+        }
+    };
+
+    println!("\nPlanned vs Actual: {} to {}", start_date, end_date);
+    println!("{:<12} {:>10} {:>10} {:>10} {:>10}", "Date", "Planned", "Actual", "Diff", "Target");
+    println!("{:-<56}", "");
+    let mut total_planned = 0.0;
+    let mut total_actual = 0.0;
+    for day in &comparison {
+        println!("{:<12} {:>10.1} {:>10.1} {:>+10.1} {:>10.0}",
+            day.date, day.planned, day.actual, day.actual - day.planned, day.target);
+        for (meal, planned, actual) in &day.meals {
+            println!("  {:<10} {:>10.1} {:>10.1} {:>+10.1}", meal.name(), planned, actual, actual - planned);
+        }
+        total_planned += day.planned;
+        total_actual += day.actual;
+    }
+    println!("{:-<56}", "");
+    println!("{:<12} {:>10.1} {:>10.1} {:>+10.1}", "Total", total_planned, total_actual, total_actual - total_planned);
+    Ok(())
+}
+
+fn add_food_to_log(food_log: &mut FoodLog, food_db: &FoodDatabase, user_profile: &UserProfile) -> Result<(), InputError> {
+    let Some(selected_food_id) = search_and_select_food(food_db, user_profile)? else {
+        return Ok(());
+    };
     
     // Try to get as basic food first
     if let Some(food) = food_db.get_basic_food(selected_food_id) {
         // Get servings
-        println!("Enter number of servings: ");
-        let mut servings = String::new();
-        io::stdin().read_line(&mut servings).expect("Failed to read input");
-        let servings: f64 = match servings.trim().parse() {
-            Ok(num) => num,
-            Err(_) => {
+        let servings = input::read_line("Enter number of servings: ")?;
+        let servings = match servings.trim().parse::<f64>() {
+            Ok(num) if num > 0.0 && num.is_finite() => num,
+            _ => {
                 println!("Invalid input for servings. Returning to menu.");
-                return;
+                return Ok(());
             }
         };
         
+        let Some(meal) = read_meal()? else {
+            println!("Invalid meal. Returning to menu.");
+            return Ok(());
+        };
+        let time = read_time()?;
+        
         // Add to log
-        if let Err(e) = food_log.add_food_entry(food, servings) {
+        if let Err(e) = food_log.add_food_entry(food, servings, meal, time) {
             println!("Error adding food to log: {}", e);
         } else {
             println!("Added {} servings of {} to log.", servings, food.identifier);
@@ -479,73 +1718,56 @@ fn add_food_to_log(food_log: &mut FoodLog, food_db: &FoodDatabase) {
         for (basic_food, quantity) in &composite_food.components {
             println!("- {} x{}", basic_food.identifier, quantity);
         }
+        if let Some(load) = composite_food.get_glycemic_load() {
+            println!("Glycemic load per serving: {:.1}", load);
+        }
+        if let Some(macros) = composite_food.get_macros() {
+            println!("Macros per serving: {}", macros.describe());
+        }
         
         // Get servings
-        println!("Enter number of servings: ");
-        let mut servings = String::new();
-        io::stdin().read_line(&mut servings).expect("Failed to read input");
-        let servings: f64 = match servings.trim().parse() {
-            Ok(num) => num,
-            Err(_) => {
+        let servings = input::read_line("Enter number of servings: ")?;
+        let servings = match servings.trim().parse::<f64>() {
+            Ok(num) if num > 0.0 && num.is_finite() => num,
+            _ => {
                 println!("Invalid input for servings. Returning to menu.");
-                return;
+                return Ok(());
             }
         };
         
-        // For composite foods, we'll add each component individually to the log
-        println!("Adding components to log...");
-        for (basic_food, quantity) in &composite_food.components {
-            if let Err(e) = food_log.add_food_entry(basic_food, servings * quantity) {
-                println!("Error adding {} to log: {}", basic_food.identifier, e);
-            } else {
-                println!("Added {} servings of {}", servings * quantity, basic_food.identifier);
-            }
+        let Some(meal) = read_meal()? else {
+            println!("Invalid meal. Returning to menu.");
+            return Ok(());
+        };
+        let time = read_time()?;
+        
+        // Log the composite as a single entry that keeps its recipe
+        if let Err(e) = food_log.add_composite_food_entry(composite_food, servings, meal, time) {
+            println!("Error adding food to log: {}", e);
+        } else {
+            println!("Added {} servings of {} to log.", servings, composite_food.identifier);
         }
-        println!("Composite food '{}' added to log.", composite_food.identifier);
     } else {
         println!("Could not find the selected food item in the database.");
     }
+    Ok(())
 }
 
 // New function to view log for a specific date
-fn view_log_for_specific_date(food_log: &FoodLog) {
-    println!("Enter date to view (YYYY-MM-DD): ");
-    let mut date = String::new();
-    io::stdin().read_line(&mut date).expect("Failed to read input");
+fn view_log_for_specific_date(food_log: &FoodLog, user_profile: &UserProfile) -> Result<(), InputError> {
+    let date = input::read_line("Enter date to view (YYYY-MM-DD): ")?;
     let date = date.trim();
     
-    if let Some(entries) = food_log.get_entries_for_date(date) {
-        println!("\nFood Log for {}", date);
-        
-        if entries.is_empty() {
-            println!("No entries for this date.");
-            return;
-        }
-        
-        let mut total_calories = 0.0;
-        println!("Food Items:");
-        println!("------------------------------------");
-        for (i, entry) in entries.iter().enumerate() {
-            let calories = entry.calories * entry.servings;
-            println!("{}. {} (x{:.1} servings) - {:.1} calories", 
-                i+1, entry.food_id, entry.servings, calories);
-            total_calories += calories;
+    if let Some(daily_log) = food_log.get_log_for_date(date) {
+        print_daily_log(daily_log, user_profile);
+        if daily_log.entries.is_empty() {
+            return Ok(());
         }
-        println!("------------------------------------");
-        println!("Total Calories: {:.1}", total_calories);
         
         // Show comparison to target
-        if let Some((_, target, difference)) = food_log.compare_to_target(date, &UserProfile{
-            name: String::new(),
-            height: 0.0,
-            weight: 0.0,
-            age: 0,
-            gender: user_profile::Gender::Male,
-            activity_level: user_profile::ActivityLevel::Sedentary,
-            target_calorie_calc_strategy: user_profile::TargetCalorieCalcStrategy::MifflinStJeor,
-            target_calorie: 0.0,
-        }) {
-            println!("Daily target: {:.1}", target);
+        if let Some(summary) = food_log.compare_to_target(date, user_profile) {
+            let difference = summary.difference();
+            println!("Daily target: {:.1}{}", summary.target, day_label(user_profile, date));
             if difference > 0.0 {
                 println!("You were {:.1} calories over your target.", difference);
             } else if difference < 0.0 {
@@ -553,86 +1775,277 @@ fn view_log_for_specific_date(food_log: &FoodLog) {
             } else {
                 println!("You exactly met your target.");
             }
+            print_remaining_macros(&summary);
         }
     } else {
         println!("No log found for date: {}", date);
     }
+    Ok(())
 }
 
-fn view_daily_log(food_log: &FoodLog) {
+fn view_daily_log(food_log: &FoodLog, user_profile: &UserProfile) {
     if let Some(daily_log) = food_log.get_current_log() {
-        println!("\nFood Log for {}", daily_log.date);
-        
-        if daily_log.entries.is_empty() {
-            println!("No entries for this date.");
-            return;
+        print_daily_log(daily_log, user_profile);
+    } else {
+        println!("No log found for the current date.");
+    }
+}
+
+// Print a day's entries grouped by meal. Entries are numbered in display order,
+// which is the order `DailyLog::entries_by_meal` returns them in.
+fn print_daily_log(daily_log: &DailyLog, user_profile: &UserProfile) {
+    println!("\nFood Log for {}", daily_log.date);
+    
+    if !daily_log.exercises.is_empty() {
+        println!("\nExercise");
+        println!("------------------------------------");
+        for exercise in &daily_log.exercises {
+            let estimated = if exercise.estimated { " (estimated)" } else { "" };
+            println!("- {} for {:.0} min - {:.1} calories burned{}",
+                exercise.activity, exercise.duration_minutes, exercise.calories_burned, estimated);
         }
-        
-        let mut total_calories = 0.0;
-        println!("Food Items:");
+    }
+    
+    if daily_log.entries.is_empty() {
+        println!("No entries for this date.");
+        return;
+    }
+    
+    let mut number = 0;
+    let mut total_calories = 0.0;
+    let mut total_glycemic_load = 0.0;
+    let mut planned_calories = 0.0;
+    for (meal, entries) in daily_log.entries_by_meal() {
+        println!("\n{}", meal.name());
         println!("------------------------------------");
-        for (i, entry) in daily_log.entries.iter().enumerate() {
+        let mut meal_calories = 0.0;
+        let mut meal_glycemic_load = 0.0;
+        for entry in entries {
+            number += 1;
             let calories = entry.calories * entry.servings;
-            println!("{}. {} (x{:.1} servings) - {:.1} calories", 
-                i+1, entry.food_id, entry.servings, calories);
-            total_calories += calories;
+            let mut time = entry.time.as_ref().map(|t| format!("[{}] ", t)).unwrap_or_default();
+            if !entry.is_eaten() {
+                // Planned entries are shown but not counted
+                time = format!("[planned] {}", time);
+                planned_calories += calories;
+            }
+            match entry.total_glycemic_load() {
+                Some(load) => {
+                    println!("{}. {}{} (x{:.1} servings) - {:.1} calories, GL {:.1}", 
+                        number, time, entry.food_id, entry.servings, calories, load);
+                    if entry.is_eaten() {
+                        meal_glycemic_load += load;
+                    }
+                }
+                None => println!("{}. {}{} (x{:.1} servings) - {:.1} calories", 
+                    number, time, entry.food_id, entry.servings, calories),
+            }
+            if entry.is_composite() {
+                for component in entry.expand() {
+                    println!("     - {} (x{:.1} servings)", component.food_id, component.servings);
+                }
+            }
+            if entry.is_eaten() {
+                meal_calories += calories;
+            }
         }
-        println!("------------------------------------");
-        println!("Total Calories: {:.1}", total_calories);
-    } else {
-        println!("No log found for the current date.");
+        match user_profile.meal_split.target_for(&meal, user_profile.target_for_date(&daily_log.date)) {
+            Some(target) => println!("{} subtotal: {:.1} / {:.0} calories", meal.name(), meal_calories, target),
+            None => println!("{} subtotal: {:.1} calories", meal.name(), meal_calories),
+        }
+        if meal_glycemic_load > 0.0 {
+            println!("{} glycemic load: {:.1}", meal.name(), meal_glycemic_load);
+        }
+        total_calories += meal_calories;
+        total_glycemic_load += meal_glycemic_load;
+    }
+    println!("------------------------------------");
+    println!("Total Calories: {:.1}", total_calories);
+    if !daily_log.exercises.is_empty() {
+        let burned = daily_log.calculate_exercise_calories();
+        println!("Exercise: -{:.1} calories", burned);
+        println!("Net Calories: {:.1}", total_calories - burned);
+    }
+    if planned_calories > 0.0 {
+        println!("Planned (not yet confirmed): {:.1} calories", planned_calories);
+    }
+    if total_glycemic_load > 0.0 {
+        println!("Total Glycemic Load: {:.1}", total_glycemic_load);
     }
+    let macros = daily_log.calculate_macros();
+    if macros != Macros::default() || user_profile.macro_targets.is_some() {
+        println!("Macros: {}", macros.describe());
+        let missing = daily_log.count_missing_macros();
+        if missing > 0 {
+            println!("{} entries have no macro data and are not counted; add macros to those foods for a complete picture.", missing);
+        }
+    }
+}
+
+// " (training day)" or " (rest day)" when the user cycles calories by day type
+fn day_label(user_profile: &UserProfile, date: &str) -> String {
+    user_profile.day_label_for_date(date)
+        .map(|label| format!(" ({})", label))
+        .unwrap_or_default()
+}
+
+// Macro targets and what is left of them, if the user has macro targets
+fn print_remaining_macros(summary: &DaySummary) {
+    let (Some(target), Some(remaining)) = (summary.macro_target, summary.remaining_macros()) else {
+        return;
+    };
+    println!("Macro targets: {}", target.describe());
+    println!("Macros eaten: {}", summary.macros.describe());
+    println!("Remaining: {}", remaining.describe());
+}
+
+// Ask which meal an entry belongs to
+fn read_meal() -> Result<Option<Meal>, InputError> {
+    println!("Select meal:");
+    println!("1. Breakfast");
+    println!("2. Lunch");
+    println!("3. Dinner");
+    println!("4. Snack");
+    println!("5. Other (enter a name)");
+    let input = input::read_line("Enter your choice: ")?;
+    Ok(match input.trim() {
+        "1" => Some(Meal::Breakfast),
+        "2" => Some(Meal::Lunch),
+        "3" => Some(Meal::Dinner),
+        "4" => Some(Meal::Snack),
+        "5" => {
+            let input = input::read_line("Enter meal name: ")?;
+            let name = input.trim();
+            if name.is_empty() {
+                None
+            } else {
+                Some(Meal::Custom(name.to_string()))
+            }
+        }
+        _ => None,
+    })
+}
+
+// Ask for an optional time of day, HH:MM
+fn read_time() -> Result<Option<String>, InputError> {
+    let input = input::read_line("Enter the time eaten (HH:MM, 'now', or press Enter to skip): ")?;
+    Ok(match input.trim() {
+        "" => None,
+        "now" => Some(chrono::Local::now().format("%H:%M").to_string()),
+        time => match chrono::NaiveTime::parse_from_str(time, "%H:%M") {
+            Ok(time) => Some(time.format("%H:%M").to_string()),
+            Err(_) => {
+                println!("Invalid time, not recorded.");
+                None
+            }
+        },
+    })
 }
 
-fn change_log_date(food_log: &mut FoodLog) {
-    println!("Enter date (YYYY-MM-DD): ");
-    let mut date = String::new();
-    io::stdin().read_line(&mut date).expect("Failed to read input");
+fn change_log_date(food_log: &mut FoodLog, food_db: &FoodDatabase) -> Result<(), InputError> {
+    let date = input::read_line("Enter date (YYYY-MM-DD): ")?;
     
-    match food_log.set_current_date(date.trim()) {
-        Ok(_) => println!("Date changed to {}", date.trim()),
+    match food_log.open_date(date.trim(), food_db) {
+        Ok(0) => println!("Date changed to {}", date.trim()),
+        Ok(planned) => println!("Date changed to {} ({} recurring entries planned)", date.trim(), planned),
         Err(e) => println!("Error changing date: {}", e),
     }
+    Ok(())
 }
 
-fn remove_food_from_log(food_log: &mut FoodLog) {
-    // First view the log so user can see what to remove
-    view_daily_log(food_log);
+// Show the current day's log and let the user pick an entry by its displayed number
+fn select_log_entry(food_log: &FoodLog, user_profile: &UserProfile, action: &str) -> Result<Option<u64>, InputError> {
+    // First view the log so user can see what to pick
+    view_daily_log(food_log, user_profile);
     
-    // Get the current log
-    if let Some(daily_log) = food_log.get_current_log() {
-        if daily_log.entries.is_empty() {
-            return; // Nothing to remove
-        }
-        
-        println!("Enter the number of the item to remove (or 0 to cancel): ");
-        let mut index = String::new();
-        io::stdin().read_line(&mut index).expect("Failed to read input");
-        let index: usize = match index.trim().parse() {
-            Ok(num) => num,
-            Err(_) => {
-                println!("Invalid input. Returning to menu.");
-                return;
-            }
-        };
-        
-        if index == 0 {
-            return;
-        } else if index > daily_log.entries.len() {
-            println!("Invalid selection.");
-            return;
+    let Some(daily_log) = food_log.get_current_log() else {
+        return Ok(None);
+    };
+    if daily_log.entries.is_empty() {
+        return Ok(None); // Nothing to pick
+    }
+    
+    let index = input::read_line(&format!("Enter the number of the item to {} (or 0 to cancel): ", action))?;
+    let index: usize = match index.trim().parse() {
+        Ok(num) => num,
+        Err(_) => {
+            println!("Invalid input. Returning to menu.");
+            return Ok(None);
         }
-        
-        let food_id = daily_log.entries[index - 1].food_id.clone();
-        
-        match food_log.remove_food_entry(&food_id) {
+    };
+    
+    // Numbers follow the grouped order shown by view_daily_log
+    let displayed: Vec<&LogEntry> = daily_log.entries_by_meal()
+        .into_iter()
+        .flat_map(|(_, entries)| entries)
+        .collect();
+    
+    if index == 0 {
+        Ok(None)
+    } else if index > displayed.len() {
+        println!("Invalid selection.");
+        Ok(None)
+    } else {
+        Ok(Some(displayed[index - 1].id))
+    }
+}
+
+fn remove_food_from_log(food_log: &mut FoodLog, user_profile: &UserProfile) -> Result<(), InputError> {
+    if let Some(entry_id) = select_log_entry(food_log, user_profile, "remove")? {
+        match food_log.remove_food_entry(entry_id) {
             Ok(_) => println!("Food removed from log."),
             Err(e) => println!("Error removing food: {}", e),
         }
     }
+    Ok(())
 }
 
-fn statistics_menu(food_log: &FoodLog, user_profile: &UserProfile) {
+fn edit_food_in_log(food_log: &mut FoodLog, user_profile: &UserProfile) -> Result<(), InputError> {
+    let Some(entry_id) = select_log_entry(food_log, user_profile, "edit")? else {
+        return Ok(());
+    };
+    
+    println!("1. Change Servings");
+    println!("2. Move to Another Meal");
+    println!("3. Move to Another Date");
+    let choice = input::read_line("Enter your choice: ")?;
+    
+    let result = match choice.trim() {
+        "1" => {
+            let servings = input::read_line("Enter new number of servings: ")?;
+            match servings.trim().parse::<f64>() {
+                Ok(servings) if servings > 0.0 && servings.is_finite() => food_log.update_entry_servings(entry_id, servings),
+                _ => {
+                    println!("Invalid input for servings. Returning to menu.");
+                    return Ok(());
+                }
+            }
+        }
+        "2" => match read_meal()? {
+            Some(meal) => food_log.move_entry_to_meal(entry_id, meal),
+            None => {
+                println!("Invalid meal. Returning to menu.");
+                return Ok(());
+            }
+        },
+        "3" => {
+            let date = input::read_line("Enter date to move to (YYYY-MM-DD): ")?;
+            food_log.move_entry_to_date(entry_id, date.trim())
+        }
+        _ => {
+            println!("Invalid choice. Returning to menu.");
+            return Ok(());
+        }
+    };
+    
+    match result {
+        Ok(_) => println!("Entry updated."),
+        Err(e) => println!("Error updating entry: {}", e),
+    }
+    Ok(())
+}
+
+fn statistics_menu(food_log: &FoodLog, user_profile: &UserProfile) -> Result<(), InputError> {
     loop {
         println!("\nStatistics Menu");
         println!("1. View Today's Summary");
@@ -640,11 +2053,12 @@ fn statistics_menu(food_log: &FoodLog, user_profile: &UserProfile) {
         println!("3. View Monthly Summary");
         println!("4. View Summary for Specific Date Range");
         println!("5. View All Logged Dates");
-        println!("6. Return to User Session");
+        println!("6. View Food Spending Report");
+        println!("7. View Glycemic Load Summary");
+        println!("8. View Weekly Calorie Budget");
+        println!("9. Return to User Session");
 
-        let mut choice = String::new();
-        println!("Enter your choice: ");
-        io::stdin().read_line(&mut choice).expect("Failed to read input");
+        let choice = input::read_line("Enter your choice: ")?;
 
         match choice.trim() {
             "1" => {
@@ -662,7 +2076,7 @@ fn statistics_menu(food_log: &FoodLog, user_profile: &UserProfile) {
             }
             "4" => {
                 // View summary for specific date range
-                custom_range_summary(food_log, user_profile);
+                back_on_cancel(custom_range_summary(food_log, user_profile))?;
             }
             "5" => {
                 // View all logged dates
@@ -676,18 +2090,166 @@ fn statistics_menu(food_log: &FoodLog, user_profile: &UserProfile) {
                     }
                 }
             }
-            "6" => break,
+            "6" => {
+                // View daily/weekly spend and value for money
+                view_spending_report(food_log, user_profile);
+            }
+            "7" => {
+                // View daily glycemic load for the last 7 days
+                view_glycemic_summary(food_log, user_profile);
+            }
+            "8" => {
+                // Remaining weekly budget with banked calories
+                view_weekly_budget(food_log, user_profile);
+            }
+            "9" => return Ok(()),
             _ => println!("Invalid choice, please try again."),
         }
     }
 }
 
+fn view_weekly_budget(food_log: &FoodLog, user_profile: &UserProfile) {
+    let Some(budget) = &user_profile.calorie_budget else {
+        println!("Weekly budget mode is off. Turn it on under 'Modify Current User'.");
+        return;
+    };
+
+    let today = chrono::Local::now().format("%Y-%m-%d").to_string();
+    let status = match weekly_budget_status(food_log, user_profile, budget, &today) {
+        Ok(status) => status,
+        Err(e) => {
+            println!("Error getting weekly budget: {}", e);
+            return;
+        }
+    };
+
+    println!("\nWeekly Calorie Budget ({} to {})", status.week_start, status.week_end);
+    println!("{:<12} {:>10} {:>10} {:>10}", "Date", "Net", "Target", "Banked");
+    println!("{:-<45}", "");
+    for (day, banked) in &status.days {
+        println!("{:<12} {:>10.1} {:>10.1} {:>+10.1}", day.date, day.net(), day.target, banked);
+    }
+    println!("{:-<45}", "");
+    println!("Weekly budget: {:.0} calories", status.budget);
+    println!("Carried forward: {:+.0} calories (cap {:.0})", status.banked, budget.bank_cap);
+    println!("Remaining this week: {:.0} calories over {} days (including today)", status.remaining, status.days_left);
+    println!("Suggested daily target for the rest of the week: {:.0} calories", status.suggested_target);
+    if status.below_minimum {
+        println!("Note: the suggestion was raised to the safe minimum. Spread the overage over the coming weeks instead.");
+    }
+}
+
+fn view_glycemic_summary(food_log: &FoodLog, user_profile: &UserProfile) {
+    let today = chrono::Local::now().date_naive();
+    let end_date = today.format("%Y-%m-%d").to_string();
+    let start_date = (today - chrono::Duration::days(6)).format("%Y-%m-%d").to_string();
+
+    let summary = match get_glycemic_summary(food_log, &start_date, &end_date) {
+        Ok(summary) => summary,
+        Err(e) => {
+            println!("Error getting glycemic load summary: {}", e);
+            return;
+        }
+    };
+
+    let thresholds = &user_profile.glycemic_thresholds;
+    println!("\nDaily Glycemic Load (low <= {:.0}, high >= {:.0})", thresholds.low, thresholds.high);
+    println!("{:<12} {:>10} {:>8} {:>10}", "Date", "Load", "Rating", "Coverage");
+    println!("------------------------------------------");
+    for day in &summary {
+        let load = match day.known_load() {
+            Some(load) => format!("{:.1}", load),
+            None => "-".to_string(),
+        };
+        let coverage = match day.coverage() {
+            Some(coverage) => format!("{:.0}%", coverage),
+            None => "-".to_string(),
+        };
+        println!("{:<12} {:>10} {:>8} {:>10}", day.date, load, thresholds.classify(day.known_load()), coverage);
+    }
+
+    if summary.iter().any(|day| day.missing > 0) {
+        println!("Coverage is the share of entries with glycemic data; the rest are not counted, so a low");
+        println!("coverage understates the load. Add GI and carbs to those foods for a complete picture.");
+    }
+}
+
+fn view_spending_report(food_log: &FoodLog, user_profile: &UserProfile) {
+    let today = chrono::Local::now().date_naive();
+    let end_date = today.format("%Y-%m-%d").to_string();
+    // Four full weeks ending today
+    let start_date = (today - chrono::Duration::days(27)).format("%Y-%m-%d").to_string();
+
+    let currency = match &user_profile.food_budget {
+        Some(budget) => budget.currency.clone(),
+        None => match most_used_currency(food_log, &start_date, &end_date) {
+            Some(currency) => currency,
+            None => {
+                println!("No priced food entries in the last 4 weeks.");
+                return;
+            }
+        },
+    };
+
+    let summary = match get_spend_summary(food_log, &start_date, &end_date, &currency) {
+        Ok(summary) => summary,
+        Err(e) => {
+            println!("Error getting spending summary: {}", e);
+            return;
+        }
+    };
+
+    println!("\nDaily Spending ({}) - last 7 days", currency);
+    println!("{:<12} {:>10} {:>12} {:>12}", "Date", "Spend", "Calories", "Cal/Unit");
+    println!("--------------------------------------------------");
+    for (date, spend, priced_calories, _) in &summary[summary.len() - 7..] {
+        let per_unit = if *spend > 0.0 { priced_calories / spend } else { 0.0 };
+        println!("{:<12} {:>10.2} {:>12.1} {:>12.1}", date, spend, priced_calories, per_unit);
+    }
+    if let Some(budget) = &user_profile.food_budget {
+        println!("Daily budget: {:.2} {}", budget.daily_amount(), budget.currency);
+    }
+
+    println!("\nWeekly Spending ({})", currency);
+    println!("{:<25} {:>10} {:>12}", "Week", "Spend", "Budget Left");
+    println!("--------------------------------------------------");
+    for week in summary.chunks(7) {
+        let spend: f64 = week.iter().map(|(_, spend, _, _)| spend).sum();
+        let label = format!("{} - {}", week[0].0, week[week.len() - 1].0);
+        match &user_profile.food_budget {
+            Some(budget) => println!("{:<25} {:>10.2} {:>12.2}", label, spend, budget.weekly_amount() - spend),
+            None => println!("{:<25} {:>10.2} {:>12}", label, spend, "-"),
+        }
+    }
+
+    let total_spend: f64 = summary.iter().map(|(_, spend, _, _)| spend).sum();
+    let priced_calories: f64 = summary.iter().map(|(_, _, calories, _)| calories).sum();
+    let total_calories: f64 = summary.iter().map(|(_, _, _, calories)| calories).sum();
+    if total_spend > 0.0 {
+        println!("\nOverall: {:.1} calories per {} over the last 4 weeks", priced_calories / total_spend, currency);
+    }
+    if total_calories > priced_calories {
+        println!("{:.1} logged calories had no {} price and are not included.", total_calories - priced_calories, currency);
+    }
+
+    if let Ok(foods) = get_cost_efficiency(food_log, &start_date, &end_date, &currency)
+        && !foods.is_empty() {
+        println!("\nCalories per {} by food (best value first):", currency);
+        for (food_id, calories, spend) in foods {
+            println!("- {}: {:.1} ({:.2} {} for {:.1} calories)", food_id, calories / spend, spend, currency, calories);
+        }
+    }
+}
+
 fn view_date_summary(food_log: &FoodLog, date: &str, user_profile: &UserProfile) {
     println!("\nSummary for {}", date);
     
-    if let Some((actual, target, difference)) = food_log.compare_to_target(date, user_profile) {
-        println!("Total calories consumed: {:.1}", actual);
-        println!("Daily target: {:.1}", target);
+    if let Some(summary) = food_log.compare_to_target(date, user_profile) {
+        let difference = summary.difference();
+        println!("Total calories consumed: {:.1}", summary.intake);
+        println!("Burned by exercise: {:.1}", summary.exercise);
+        println!("Net calories: {:.1}", summary.net());
+        println!("Daily target: {:.1}{}", summary.target, day_label(user_profile, date));
         println!("Difference: {:.1}", difference);
         
         if difference > 0.0 {
@@ -697,6 +2259,7 @@ fn view_date_summary(food_log: &FoodLog, date: &str, user_profile: &UserProfile)
         } else {
             println!("You have exactly met your target.");
         }
+        print_remaining_macros(&summary);
     } else {
         println!("No log data for this date.");
     }
@@ -716,46 +2279,59 @@ fn view_range_summary(food_log: &FoodLog, days: i64, user_profile: &UserProfile)
     }
 }
 
-fn custom_range_summary(food_log: &FoodLog, user_profile: &UserProfile) {
-    println!("Enter start date (YYYY-MM-DD): ");
-    let mut start_date = String::new();
-    io::stdin().read_line(&mut start_date).expect("Failed to read input");
+fn custom_range_summary(food_log: &FoodLog, user_profile: &UserProfile) -> Result<(), InputError> {
+    let start_date = input::read_line("Enter start date (YYYY-MM-DD): ")?;
     
-    println!("Enter end date (YYYY-MM-DD): ");
-    let mut end_date = String::new();
-    io::stdin().read_line(&mut end_date).expect("Failed to read input");
+    let end_date = input::read_line("Enter end date (YYYY-MM-DD): ")?;
     
     match get_calorie_summary(food_log, start_date.trim(), end_date.trim(), user_profile) {
         Ok(summary) => display_summary_table(summary),
         Err(e) => println!("Error getting summary: {}", e),
     }
+    Ok(())
 }
 
-fn display_summary_table(summary: Vec<(String, f64, f64, f64)>) {
+fn display_summary_table(summary: Vec<DaySummary>) {
     if summary.is_empty() {
         println!("No data available for the selected date range.");
         return;
     }
     
-    println!("{:<12} {:>10} {:>10} {:>10}", "Date", "Actual", "Target", "Difference");
-    println!("--------------------------------------------------");
+    // Remaining macros are only shown when the user has macro targets
+    let show_macros = summary.iter().any(|day| day.macro_target.is_some());
+    let macro_header = if show_macros {
+        format!(" {:>9} {:>9} {:>9}", "Protein", "Carbs", "Fat")
+    } else {
+        String::new()
+    };
+    println!("{:<12} {:>10} {:>10} {:>10} {:>10} {:>10}{}", "Date", "Intake", "Exercise", "Net", "Target", "Difference", macro_header);
+    println!("------------------------------------------------------------------------{}", if show_macros { "------------------------------" } else { "" });
     
-    let mut total_actual = 0.0;
+    let mut total_intake = 0.0;
+    let mut total_exercise = 0.0;
     let mut total_target = 0.0;
     
-    for (date, actual, target, diff) in &summary {
-        println!("{:<12} {:>10.1} {:>10.1} {:>10.1}", date, actual, target, diff);
-        total_actual += actual;
-        total_target += target;
+    for day in &summary {
+        let remaining = match day.remaining_macros() {
+            Some(left) => format!(" {:>8.0}g {:>8.0}g {:>8.0}g", left.protein, left.carbs, left.fat),
+            None => String::new(),
+        };
+        println!("{:<12} {:>10.1} {:>10.1} {:>10.1} {:>10.1} {:>10.1}{}",
+            day.date, day.intake, day.exercise, day.net(), day.target, day.difference(), remaining);
+        total_intake += day.intake;
+        total_exercise += day.exercise;
+        total_target += day.target;
     }
     
-    println!("--------------------------------------------------");
-    let avg_actual = total_actual / summary.len() as f64;
-    let avg_target = total_target / summary.len() as f64;
-    let avg_diff = avg_actual - avg_target;
-    
-    println!("{:<12} {:>10.1} {:>10.1} {:>10.1}", 
-        "Average", avg_actual, avg_target, avg_diff);
-    println!("{:<12} {:>10.1} {:>10.1} {:>10.1}", 
-        "Total", total_actual, total_target, total_actual - total_target);
+    println!("------------------------------------------------------------------------{}", if show_macros { "------------------------------" } else { "" });
+    let days = summary.len() as f64;
+    let total_net = total_intake - total_exercise;
+    
+    println!("{:<12} {:>10.1} {:>10.1} {:>10.1} {:>10.1} {:>10.1}", 
+        "Average", total_intake / days, total_exercise / days, total_net / days, total_target / days, (total_net - total_target) / days);
+    println!("{:<12} {:>10.1} {:>10.1} {:>10.1} {:>10.1} {:>10.1}", 
+        "Total", total_intake, total_exercise, total_net, total_target, total_net - total_target);
+    if show_macros {
+        println!("Protein, Carbs and Fat are grams left to reach your macro targets.");
+    }
 }
\ No newline at end of file
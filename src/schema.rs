@@ -0,0 +1,652 @@
+use std::fs;
+use std::io;
+use std::path::Path;
+use std::collections::HashMap;
+use serde::Serialize;
+use serde::de::DeserializeOwned;
+use serde_yaml::{Mapping, Value};
+
+// Key written at the top of every data file
+const VERSION_KEY: &str = "schema_version";
+
+// A migration upgrades a parsed document from version N to N + 1 in place
+type Migration = fn(&mut Value) -> Result<(), io::Error>;
+
+// The different kinds of data files we persist
+#[derive(Debug, Clone, Copy)]
+pub enum SchemaKind {
+    BasicFoods,
+    CompositeFoods,
+    FoodLog,
+    Users,
+}
+
+impl SchemaKind {
+    // Migration registry: entry i upgrades a document from version i to i + 1.
+    // Version 0 is the original unversioned format.
+    fn migrations(&self) -> &'static [Migration] {
+        match self {
+            SchemaKind::BasicFoods => &[add_version_header, forget_unrecorded_dietary_flags],
+            SchemaKind::CompositeFoods => &[add_version_header],
+            SchemaKind::FoodLog => &[add_version_header, assign_entry_ids, drop_action_history, assign_rule_ids],
+            SchemaKind::Users => &[wrap_users_list, seed_target_history, assign_user_ids, snapshot_macro_targets],
+        }
+    }
+
+    // The newest version this build knows how to read and write
+    pub fn current_version(&self) -> u32 {
+        self.migrations().len() as u32
+    }
+
+    fn name(&self) -> &'static str {
+        match self {
+            SchemaKind::BasicFoods => "basic foods",
+            SchemaKind::CompositeFoods => "composite foods",
+            SchemaKind::FoodLog => "food log",
+            SchemaKind::Users => "users",
+        }
+    }
+}
+
+// Load a versioned YAML file, upgrading it on disk if it uses an older schema.
+// Returns Ok(None) when the file does not exist.
+pub fn load_versioned<T: DeserializeOwned>(path: &str, kind: SchemaKind) -> Result<Option<T>, io::Error> {
+    load(path, kind, true)
+}
+
+// Like `load_versioned`, but an older file is only upgraded in memory. Nothing
+// is written, so this is safe for lookups that must not change other files.
+pub fn read_versioned<T: DeserializeOwned>(path: &str, kind: SchemaKind) -> Result<Option<T>, io::Error> {
+    load(path, kind, false)
+}
+
+fn load<T: DeserializeOwned>(path: &str, kind: SchemaKind, upgrade_on_disk: bool) -> Result<Option<T>, io::Error> {
+    if !Path::new(path).exists() {
+        return Ok(None);
+    }
+
+    let contents = fs::read_to_string(path)?;
+    let mut document: Value = serde_yaml::from_str(&contents)
+        .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))?;
+
+    let file_version = read_version(&document)?;
+    let current_version = kind.current_version();
+
+    if file_version > current_version {
+        return Err(io::Error::new(
+            io::ErrorKind::Unsupported,
+            format!(
+                "'{}' uses {} schema version {}, but this version of the app only supports up to {}. Please upgrade the app.",
+                path, kind.name(), file_version, current_version
+            )
+        ));
+    }
+
+    if file_version < current_version && !upgrade_on_disk {
+        for migration in &kind.migrations()[file_version as usize..] {
+            migration(&mut document)?;
+        }
+    } else if file_version < current_version {
+        // Keep a copy of the original file before touching it
+        let backup_path = format!("{}.v{}.bak", path, file_version);
+        fs::copy(path, &backup_path)?;
+
+        for migration in &kind.migrations()[file_version as usize..] {
+            migration(&mut document)?;
+        }
+
+        // Write the upgraded document back so the migration only runs once
+        set_version(&mut document, current_version)?;
+        let yaml = serde_yaml::to_string(&document)
+            .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))?;
+        fs::write(path, yaml)?;
+
+        println!("Upgraded {} file '{}' from schema version {} to {} (backup saved to '{}')",
+            kind.name(), path, file_version, current_version, backup_path);
+    }
+
+    if let Value::Mapping(map) = &mut document {
+        map.remove(VERSION_KEY);
+    }
+
+    let data = serde_yaml::from_value(document)
+        .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))?;
+    Ok(Some(data))
+}
+
+// Whether a load failed because the file was written by a newer version of
+// the app. Saving over such a file would lose data, so callers must not.
+pub fn is_unsupported_version(error: &io::Error) -> bool {
+    error.kind() == io::ErrorKind::Unsupported
+}
+
+// Serialize a value to YAML with the current schema version as the first key
+pub fn to_versioned_yaml<T: Serialize>(value: &T, kind: SchemaKind) -> Result<String, io::Error> {
+    let mut document = serde_yaml::to_value(value)
+        .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))?;
+    set_version(&mut document, kind.current_version())?;
+
+    serde_yaml::to_string(&document)
+        .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))
+}
+
+fn read_version(document: &Value) -> Result<u32, io::Error> {
+    match document {
+        Value::Mapping(map) => match map.get(VERSION_KEY) {
+            Some(version) => version.as_u64()
+                .map(|v| v as u32)
+                .ok_or_else(|| io::Error::new(
+                    io::ErrorKind::InvalidData,
+                    format!("Invalid {} value: {:?}", VERSION_KEY, version)
+                )),
+            None => Ok(0),
+        },
+        // Files written before versioning may be bare lists (e.g. users.yaml)
+        _ => Ok(0),
+    }
+}
+
+fn set_version(document: &mut Value, version: u32) -> Result<(), io::Error> {
+    let Value::Mapping(map) = document else {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidData,
+            "Expected a mapping at the top level of the data file"
+        ));
+    };
+
+    // Rebuild the mapping so the version header comes first
+    let mut versioned = Mapping::new();
+    versioned.insert(Value::from(VERSION_KEY), Value::from(version));
+    for (key, value) in std::mem::take(map) {
+        if key.as_str() != Some(VERSION_KEY) {
+            versioned.insert(key, value);
+        }
+    }
+    *map = versioned;
+
+    Ok(())
+}
+
+// Migrations
+
+// v0 -> v1 for files whose layout did not change: the header is added by the loader
+fn add_version_header(_document: &mut Value) -> Result<(), io::Error> {
+    Ok(())
+}
+
+// v0 -> v1 for users.yaml: the bare list becomes `users:` under the header,
+// and profiles written before `gender` existed get the old implicit default
+fn wrap_users_list(document: &mut Value) -> Result<(), io::Error> {
+    let mut users = match std::mem::take(document) {
+        Value::Sequence(users) => users,
+        Value::Null => Vec::new(),
+        _ => return Err(io::Error::new(
+            io::ErrorKind::InvalidData,
+            "Expected a list of users in unversioned users file"
+        )),
+    };
+
+    for user in &mut users {
+        if let Value::Mapping(profile) = user
+            && !profile.contains_key("gender") {
+            profile.insert(Value::from("gender"), Value::from("Male"));
+        }
+    }
+
+    let mut wrapper = Mapping::new();
+    wrapper.insert(Value::from("users"), Value::Sequence(users));
+    *document = Value::Mapping(wrapper);
+
+    Ok(())
+}
+
+// v1 -> v2 for basic foods: dietary flags became yes/no/unknown. A stored
+// false only ever meant "not known to be", so it becomes unknown rather
+// than a definite no that would hide the food from restricted searches.
+fn forget_unrecorded_dietary_flags(document: &mut Value) -> Result<(), io::Error> {
+    let Some(Value::Sequence(foods)) = document.get_mut("basic_foods") else {
+        return Ok(());
+    };
+
+    for food in foods {
+        if let Some(Value::Mapping(dietary)) = food.get_mut("dietary") {
+            dietary.retain(|_, value| value.as_bool() != Some(false));
+        }
+    }
+
+    Ok(())
+}
+
+// v1 -> v2 for food logs: entries are no longer merged by food, so each one
+// gets an ID that is unique across the user's log
+fn assign_entry_ids(document: &mut Value) -> Result<(), io::Error> {
+    let Value::Mapping(log) = document else {
+        return Err(io::Error::new(io::ErrorKind::InvalidData, "Expected a mapping in food log file"));
+    };
+
+    let mut next_id: u64 = 1;
+    if let Some(Value::Sequence(daily_logs)) = log.get_mut("daily_logs") {
+        for daily_log in daily_logs {
+            if let Some(Value::Sequence(entries)) = daily_log.get_mut("entries") {
+                for entry in entries {
+                    if let Value::Mapping(entry) = entry {
+                        entry.insert(Value::from("id"), Value::from(next_id));
+                        next_id += 1;
+                    }
+                }
+            }
+        }
+    }
+
+    log.insert(Value::from("next_entry_id"), Value::from(next_id));
+
+    Ok(())
+}
+
+// v2 -> v3 for food logs: undo history records changed from per-kind actions
+// to before/after entry snapshots. Old records cannot be replayed exactly, so
+// they are dropped; the logged entries themselves are untouched.
+fn drop_action_history(document: &mut Value) -> Result<(), io::Error> {
+    let Value::Mapping(log) = document else {
+        return Err(io::Error::new(io::ErrorKind::InvalidData, "Expected a mapping in food log file"));
+    };
+
+    log.remove("history");
+    log.remove("redo_stack");
+
+    Ok(())
+}
+
+// v3 -> v4 for food logs: recurrence rules get IDs from the entry sequence
+// and each day records the IDs of the rules applied to it instead of their
+// names, so a new rule reusing a deleted rule's name still gets planned.
+// Names of rules that no longer exist are dropped.
+fn assign_rule_ids(document: &mut Value) -> Result<(), io::Error> {
+    let Value::Mapping(log) = document else {
+        return Err(io::Error::new(io::ErrorKind::InvalidData, "Expected a mapping in food log file"));
+    };
+
+    let mut next_id = log.get("next_entry_id").and_then(Value::as_u64).unwrap_or(1).max(1);
+    let mut rule_ids: HashMap<String, u64> = HashMap::new();
+    if let Some(Value::Sequence(rules)) = log.get_mut("recurrence_rules") {
+        for rule in rules {
+            if let Value::Mapping(rule) = rule {
+                if let Some(name) = rule.get("name").and_then(Value::as_str) {
+                    rule_ids.insert(name.to_string(), next_id);
+                }
+                rule.insert(Value::from("id"), Value::from(next_id));
+                next_id += 1;
+            }
+        }
+    }
+
+    if let Some(Value::Sequence(daily_logs)) = log.get_mut("daily_logs") {
+        for daily_log in daily_logs {
+            if let Some(Value::Sequence(applied)) = daily_log.get_mut("applied_rules") {
+                *applied = applied.iter()
+                    .filter_map(|name| name.as_str().and_then(|name| rule_ids.get(name)))
+                    .map(|id| Value::from(*id))
+                    .collect();
+            }
+        }
+    }
+
+    log.insert(Value::from("next_entry_id"), Value::from(next_id));
+
+    Ok(())
+}
+
+// v1 -> v2 for users.yaml: targets are now snapshotted when they change.
+// The target each user already had is taken to have applied since the start,
+// so existing summaries keep their numbers.
+fn seed_target_history(document: &mut Value) -> Result<(), io::Error> {
+    let Some(Value::Sequence(users)) = document.get_mut("users") else {
+        return Ok(());
+    };
+
+    for user in users {
+        let Value::Mapping(profile) = user else {
+            continue;
+        };
+        if profile.contains_key("target_history") {
+            continue;
+        }
+
+        let mut snapshot = Mapping::new();
+        snapshot.insert(Value::from("effective_date"), Value::from("1970-01-01"));
+        for (field, key) in [
+            ("target_calorie", "target_calorie"),
+            ("target_calorie_calc_strategy", "strategy"),
+            ("height", "height"),
+            ("weight", "weight"),
+            ("age", "age"),
+            ("gender", "gender"),
+            ("activity_level", "activity_level"),
+        ] {
+            let value = profile.get(field).cloned().ok_or_else(|| io::Error::new(
+                io::ErrorKind::InvalidData,
+                format!("User profile is missing '{}'", field)
+            ))?;
+            snapshot.insert(Value::from(key), value);
+        }
+        profile.insert(Value::from("target_history"), Value::Sequence(vec![Value::Mapping(snapshot)]));
+    }
+
+    Ok(())
+}
+
+// v2 -> v3 for users.yaml: each user gets a stable ID, used to name their log
+// file instead of their name, and their existing log file is moved over.
+// Doing the move here rather than on every load keeps a later user with a
+// reused name from picking up someone else's leftover log.
+fn assign_user_ids(document: &mut Value) -> Result<(), io::Error> {
+    assign_user_ids_in(document, crate::food_log::LOG_DIR)
+}
+
+fn assign_user_ids_in(document: &mut Value, log_dir: &str) -> Result<(), io::Error> {
+    let Some(Value::Sequence(users)) = document.get_mut("users") else {
+        return Ok(());
+    };
+
+    let mut taken: Vec<String> = users.iter()
+        .filter_map(|user| user.get("id").and_then(Value::as_str).map(str::to_string))
+        .collect();
+    for (index, user) in users.iter_mut().enumerate() {
+        if let Value::Mapping(profile) = user
+            && !profile.contains_key("id") {
+            let name = profile.get("name").and_then(Value::as_str).unwrap_or_default().to_string();
+            let id = (0..)
+                .map(|attempt| legacy_user_id(index, &name, attempt))
+                .find(|id| !taken.contains(id))
+                .unwrap_or_default();
+            if crate::food_log::adopt_name_based_log(log_dir, &name, &id)? {
+                println!("Moved food log of '{}' to a file named after their user ID ({}).", name, id);
+            }
+            taken.push(id.clone());
+            profile.insert(Value::from("id"), Value::from(id));
+        }
+    }
+
+    Ok(())
+}
+
+// IDs for users from before IDs existed come from their place in the file
+// and their name rather than the clock. Logs are moved before the upgraded
+// file is written, so if that write never happens the next run picks the
+// same IDs again and finds the logs already in place.
+fn legacy_user_id(index: usize, name: &str, attempt: u64) -> String {
+    // FNV-1a, which unlike the std hasher is fixed across Rust releases
+    let mut hash: u64 = 0xcbf29ce484222325;
+    for byte in format!("{}:{}:{}", index, name, attempt).bytes() {
+        hash ^= byte as u64;
+        hash = hash.wrapping_mul(0x100000001b3);
+    }
+    format!("u{:x}", hash)
+}
+
+// v3 -> v4 for users.yaml: macro targets are now snapshotted with the
+// calorie target. Earlier snapshots get the user's current macro targets,
+// which is what they were read with until now.
+fn snapshot_macro_targets(document: &mut Value) -> Result<(), io::Error> {
+    let Some(Value::Sequence(users)) = document.get_mut("users") else {
+        return Ok(());
+    };
+
+    for user in users {
+        let Value::Mapping(profile) = user else {
+            continue;
+        };
+        let macro_targets = profile.get("macro_targets").cloned().unwrap_or(Value::Null);
+        if let Some(Value::Sequence(history)) = profile.get_mut("target_history") {
+            for snapshot in history {
+                if let Value::Mapping(snapshot) = snapshot
+                    && !snapshot.contains_key("macro_targets") {
+                    snapshot.insert(Value::from("macro_targets"), macro_targets.clone());
+                }
+            }
+        }
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::food_database::BasicFood;
+    use crate::food_log::DailyLog;
+    use crate::user_profile::UserProfile;
+
+    // A data file in its own temporary directory
+    fn test_file(name: &str, contents: &str) -> String {
+        let dir = std::env::temp_dir().join(format!("yada_schema_{}_{}", name, std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let path = dir.join(format!("{}.yaml", name)).to_string_lossy().to_string();
+        fs::write(&path, contents).unwrap();
+        path
+    }
+
+    fn cleanup(path: &str) {
+        let _ = fs::remove_dir_all(Path::new(path).parent().unwrap());
+    }
+
+    // Loads an unversioned file, checks it was upgraded on disk with a
+    // backup of the original, and that saving and loading again is lossless
+    fn upgrade(name: &str, contents: &str, kind: SchemaKind) -> Value {
+        let path = test_file(name, contents);
+        let loaded: Value = load_versioned(&path, kind).unwrap().unwrap();
+
+        let backup = fs::read_to_string(format!("{}.v0.bak", path)).unwrap();
+        assert_eq!(backup, contents);
+        let upgraded: Value = serde_yaml::from_str(&fs::read_to_string(&path).unwrap()).unwrap();
+        assert_eq!(read_version(&upgraded).unwrap(), kind.current_version());
+
+        fs::write(&path, to_versioned_yaml(&loaded, kind).unwrap()).unwrap();
+        let reloaded: Value = load_versioned(&path, kind).unwrap().unwrap();
+        assert_eq!(reloaded, loaded);
+
+        cleanup(&path);
+        loaded
+    }
+
+    #[test]
+    fn upgrades_basic_foods() {
+        let loaded = upgrade("basic", "\
+basic_foods:
+- identifier: Apple
+  keywords:
+  - fruit
+  calories_per_serving: 95.0
+", SchemaKind::BasicFoods);
+
+        let foods: Vec<BasicFood> = serde_yaml::from_value(loaded["basic_foods"].clone()).unwrap();
+        assert_eq!(foods.len(), 1);
+        assert_eq!(foods[0].identifier, "Apple");
+        assert_eq!(foods[0].calories_per_serving, 95.0);
+    }
+
+    #[test]
+    fn unrecorded_dietary_flags_become_unknown() {
+        let path = test_file("dietary", "\
+schema_version: 1
+basic_foods:
+- identifier: Tofu
+  keywords: []
+  calories_per_serving: 80.0
+  dietary:
+    allergens:
+    - Soy
+    vegan: true
+    vegetarian: true
+    halal: false
+    gluten_free: false
+");
+        let loaded: Value = load_versioned(&path, SchemaKind::BasicFoods).unwrap().unwrap();
+        let foods: Vec<BasicFood> = serde_yaml::from_value(loaded["basic_foods"].clone()).unwrap();
+        let dietary = &foods[0].dietary;
+        assert_eq!(dietary.vegan, Some(true));
+        assert_eq!(dietary.vegetarian, Some(true));
+        assert_eq!(dietary.halal, None);
+        assert_eq!(dietary.is_gluten_free(), None);
+        assert_eq!(dietary.allergens.len(), 1);
+
+        cleanup(&path);
+    }
+
+    #[test]
+    fn upgrades_composite_foods() {
+        let loaded = upgrade("composite", "\
+composite_foods:
+- identifier: Fruit Salad
+  keywords:
+  - dessert
+  components:
+  - food_id: Apple
+    quantity: 1.0
+", SchemaKind::CompositeFoods);
+
+        let components = &loaded["composite_foods"][0]["components"];
+        assert_eq!(components[0]["food_id"].as_str(), Some("Apple"));
+        assert_eq!(components[0]["quantity"].as_f64(), Some(1.0));
+    }
+
+    #[test]
+    fn upgrades_food_log() {
+        let loaded = upgrade("log", "\
+user_name: Someone
+daily_logs:
+- date: 2025-04-06
+  entries:
+  - food_id: Apple
+    servings: 2.0
+    calories: 95.0
+  - food_id: Brown Rice
+    servings: 1.0
+    calories: 215.0
+- date: 2025-04-07
+  entries:
+  - food_id: Apple
+    servings: 1.0
+    calories: 95.0
+", SchemaKind::FoodLog);
+
+        let daily_logs: Vec<DailyLog> = serde_yaml::from_value(loaded["daily_logs"].clone()).unwrap();
+        let ids: Vec<u64> = daily_logs.iter()
+            .flat_map(|log| log.entries.iter().map(|entry| entry.id))
+            .collect();
+        assert_eq!(ids, vec![1, 2, 3]);
+        assert_eq!(loaded["next_entry_id"].as_u64(), Some(4));
+        assert_eq!(daily_logs[0].entries[0].servings, 2.0);
+    }
+
+    #[test]
+    fn upgrades_users() {
+        let loaded = upgrade("users", "\
+- name: Schema Test User
+  height: 180.0
+  weight: 80.0
+  age: 30
+  activity_level: Sedentary
+  target_calorie_calc_strategy: HarrisBenedict
+  target_calorie: 2200.0
+", SchemaKind::Users);
+
+        let users: Vec<UserProfile> = serde_yaml::from_value(loaded["users"].clone()).unwrap();
+        assert_eq!(users.len(), 1);
+        assert_eq!(users[0].name, "Schema Test User");
+        assert!(!users[0].id.is_empty());
+        // The old implicit default for profiles written before `gender`
+        assert_eq!(loaded["users"][0]["gender"].as_str(), Some("Male"));
+        // The existing target is kept for past dates
+        assert_eq!(loaded["users"][0]["target_history"][0]["target_calorie"].as_f64(), Some(2200.0));
+    }
+
+    #[test]
+    fn name_based_logs_move_to_user_ids() {
+        let dir = std::env::temp_dir().join(format!("yada_schema_adopt_{}", std::process::id()));
+        let log_dir = dir.to_string_lossy().to_string();
+        fs::create_dir_all(dir.join("Sam")).unwrap();
+        fs::write(dir.join("Alex_logs.yaml"), "logs: []\n").unwrap();
+        fs::write(dir.join("Sam/Slash_logs.yaml"), "logs: []\n").unwrap();
+        let original: Value = serde_yaml::from_str("users:\n- name: Alex\n- name: Sam/Slash\n").unwrap();
+
+        let mut document = original.clone();
+        assign_user_ids_in(&mut document, &log_dir).unwrap();
+        let ids: Vec<String> = (0..2).map(|user| document["users"][user]["id"].as_str().unwrap().to_string()).collect();
+        assert_ne!(ids[0], ids[1]);
+        for id in &ids {
+            assert!(dir.join(format!("{}_logs.yaml", id)).is_file());
+        }
+        assert!(!dir.join("Alex_logs.yaml").exists());
+
+        // Running again, as after a crash before the upgraded file was
+        // written, gives the same IDs and so still finds the moved logs
+        let mut rerun = original.clone();
+        assign_user_ids_in(&mut rerun, &log_dir).unwrap();
+        assert_eq!(rerun, document);
+
+        let _ = fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn applied_rules_refer_to_rule_ids() {
+        let mut document: Value = serde_yaml::from_str("\
+next_entry_id: 5
+daily_logs:
+- date: 2025-04-07
+  entries: []
+  applied_rules: [Breakfast, Deleted]
+recurrence_rules:
+- name: Lunch
+- name: Breakfast
+").unwrap();
+        assign_rule_ids(&mut document).unwrap();
+
+        assert_eq!(document["recurrence_rules"][0]["id"].as_u64(), Some(5));
+        assert_eq!(document["recurrence_rules"][1]["id"].as_u64(), Some(6));
+        let applied: Vec<u64> = serde_yaml::from_value(document["daily_logs"][0]["applied_rules"].clone()).unwrap();
+        assert_eq!(applied, vec![6]);
+        assert_eq!(document["next_entry_id"].as_u64(), Some(7));
+    }
+
+    #[test]
+    fn refuses_newer_version() {
+        for kind in [SchemaKind::BasicFoods, SchemaKind::CompositeFoods, SchemaKind::FoodLog, SchemaKind::Users] {
+            let contents = format!("schema_version: {}\n", kind.current_version() + 1);
+            let path = test_file(&format!("newer_{}", kind.name().replace(' ', "_")), &contents);
+
+            let error = load_versioned::<Value>(&path, kind).unwrap_err();
+            assert!(is_unsupported_version(&error));
+            // The file is left exactly as it was, with no backup
+            assert_eq!(fs::read_to_string(&path).unwrap(), contents);
+            assert!(!Path::new(&format!("{}.v{}.bak", path, kind.current_version() + 1)).exists());
+
+            cleanup(&path);
+        }
+    }
+
+    #[test]
+    fn read_only_upgrade_leaves_file_alone() {
+        let contents = "user_name: Someone\ndaily_logs: []\n";
+        let path = test_file("read_only", contents);
+
+        let loaded: Value = read_versioned(&path, SchemaKind::FoodLog).unwrap().unwrap();
+        assert_eq!(loaded["next_entry_id"].as_u64(), Some(1));
+        assert_eq!(fs::read_to_string(&path).unwrap(), contents);
+        assert!(!Path::new(&format!("{}.v0.bak", path)).exists());
+
+        cleanup(&path);
+    }
+
+    #[test]
+    fn current_version_is_not_migrated() {
+        let contents = format!("schema_version: {}\nbasic_foods: []\n", SchemaKind::BasicFoods.current_version());
+        let path = test_file("current", &contents);
+
+        let loaded: Value = load_versioned(&path, SchemaKind::BasicFoods).unwrap().unwrap();
+        assert!(loaded.get(VERSION_KEY).is_none());
+        assert_eq!(fs::read_to_string(&path).unwrap(), contents);
+
+        cleanup(&path);
+    }
+}
@@ -0,0 +1,209 @@
+use std::io;
+use chrono::NaiveDate;
+
+use crate::food_log::{DaySummary, FoodLog, get_calorie_summary};
+use crate::user_profile::UserProfile;
+
+// Energy stored or released per kg of body weight change
+pub const CALORIES_PER_KG: f64 = 7700.0;
+
+// Rolling window used when adopting an estimate as the target
+pub const DEFAULT_WINDOW_DAYS: i64 = 28;
+
+// A day counts as fully logged once this share of its target was eaten.
+// Days with less are most likely partly logged, and would drag the intake
+// average, and so the estimate, down.
+const COMPLETE_DAY_FRACTION: f64 = 0.5;
+
+pub fn is_complete_day(day: &DaySummary) -> bool {
+    day.intake > 0.0 && day.intake >= day.target * COMPLETE_DAY_FRACTION
+}
+
+// How much to trust an estimate, based on how complete the data is
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Confidence {
+    Low,
+    Medium,
+    High,
+}
+
+impl Confidence {
+    pub fn name(&self) -> &'static str {
+        match self {
+            Confidence::Low => "Low",
+            Confidence::Medium => "Medium",
+            Confidence::High => "High",
+        }
+    }
+}
+
+// Maintenance calories estimated from what was eaten and how weight changed
+#[derive(Debug, Clone)]
+pub struct TdeeEstimate {
+    pub tdee: f64,
+    pub average_intake: f64,   // Net of logged exercise, over fully logged days
+    pub weekly_change: f64,    // Trend weight change in kg per week
+    pub logged_days: usize,    // Fully logged days
+    pub partial_days: usize,   // Days with some food logged, left out as incomplete
+    pub weigh_ins: usize,
+    pub window_days: i64,
+    pub confidence: Confidence,
+}
+
+// Estimate total daily energy expenditure over the `window_days` ending on
+// `end_date`. Only fully logged days count towards the intake average.
+// Returns Ok(None) when there is not enough data: at least a week of fully
+// logged days and two weigh-ins a week or more apart.
+pub fn estimate_tdee(
+    food_log: &FoodLog,
+    user_profile: &UserProfile,
+    end_date: &str,
+    window_days: i64
+) -> Result<Option<TdeeEstimate>, io::Error> {
+    let end = NaiveDate::parse_from_str(end_date, "%Y-%m-%d").map_err(|_| io::Error::new(
+        io::ErrorKind::InvalidInput,
+        "Invalid date format. Use YYYY-MM-DD."
+    ))?;
+    let start_date = (end - chrono::Duration::days(window_days - 1)).format("%Y-%m-%d").to_string();
+
+    let days = get_calorie_summary(food_log, &start_date, end_date, user_profile)?;
+
+    // Trend weight at each weigh-in inside the window
+    let trend: Vec<(String, f64, f64)> = user_profile.weight_journal.trend()
+        .into_iter()
+        .filter(|(date, _, _)| date.as_str() >= start_date.as_str() && date.as_str() <= end_date)
+        .collect();
+
+    Ok(estimate_from(&days, &trend, window_days))
+}
+
+// The estimate from a window's daily summaries and its (date, weight, trend
+// weight) weigh-ins
+fn estimate_from(days: &[DaySummary], trend: &[(String, f64, f64)], window_days: i64) -> Option<TdeeEstimate> {
+    let logged: Vec<f64> = days.iter()
+        .filter(|day| is_complete_day(day))
+        .map(|day| day.net())
+        .collect();
+    let partial_days = days.iter()
+        .filter(|day| day.intake > 0.0 && !is_complete_day(day))
+        .count();
+
+    let (first, last) = (trend.first()?, trend.last()?);
+    let span_days = match (
+        NaiveDate::parse_from_str(&first.0, "%Y-%m-%d"),
+        NaiveDate::parse_from_str(&last.0, "%Y-%m-%d"),
+    ) {
+        (Ok(first_date), Ok(last_date)) => (last_date - first_date).num_days(),
+        _ => 0,
+    };
+    if logged.len() < 7 || span_days < 7 {
+        return None;
+    }
+
+    let average_intake = logged.iter().fold(0.0, |total, intake| total + intake) / logged.len() as f64;
+    let daily_change = (last.2 - first.2) / span_days as f64;
+    let tdee = average_intake - daily_change * CALORIES_PER_KG;
+
+    // Partly logged days do not count as coverage
+    let coverage = logged.len() as f64 / window_days as f64;
+    let confidence = if coverage >= 0.8 && trend.len() >= 8 {
+        Confidence::High
+    } else if coverage >= 0.5 && trend.len() >= 4 {
+        Confidence::Medium
+    } else {
+        Confidence::Low
+    };
+
+    Some(TdeeEstimate {
+        tdee: tdee.round(),
+        average_intake,
+        weekly_change: daily_change * 7.0,
+        logged_days: logged.len(),
+        partial_days,
+        weigh_ins: trend.len(),
+        window_days,
+        confidence,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::Duration;
+    use crate::food_database::Macros;
+
+    const START: &str = "2024-01-01";
+
+    fn date(offset: i64) -> String {
+        let start = NaiveDate::parse_from_str(START, "%Y-%m-%d").unwrap();
+        (start + Duration::days(offset)).format("%Y-%m-%d").to_string()
+    }
+
+    // `window` days of summaries with a 2500 calorie target, eating
+    // `intake(day)` on each
+    fn days(window: i64, intake: impl Fn(i64) -> f64) -> Vec<DaySummary> {
+        (0..window).map(|offset| DaySummary {
+            date: date(offset),
+            intake: intake(offset),
+            exercise: 0.0,
+            target: 2500.0,
+            macros: Macros::default(),
+            macro_target: None,
+        }).collect()
+    }
+
+    // A weigh-in every `every` days, with the trend changing by `per_day` kg a day
+    fn weigh_ins(window: i64, every: i64, per_day: f64) -> Vec<(String, f64, f64)> {
+        (0..window).step_by(every as usize).map(|offset| {
+            let weight = 80.0 + per_day * offset as f64;
+            (date(offset), weight, weight)
+        }).collect()
+    }
+
+    #[test]
+    fn estimate_from_intake_and_weight_change() {
+        // Losing 0.5 kg a week on 2000 calories means burning 550 more a day
+        let estimate = estimate_from(&days(28, |_| 2000.0), &weigh_ins(28, 1, -0.5 / 7.0), 28).unwrap();
+        assert_eq!(estimate.tdee, 2550.0);
+        assert_eq!(estimate.logged_days, 28);
+        assert!((estimate.weekly_change + 0.5).abs() < 1e-9);
+    }
+
+    #[test]
+    fn partly_logged_days_are_left_out() {
+        // Every fourth day only breakfast was logged
+        let summaries = days(28, |day| if day % 4 == 0 { 400.0 } else { 2000.0 });
+        let estimate = estimate_from(&summaries, &weigh_ins(28, 1, 0.0), 28).unwrap();
+        assert_eq!(estimate.tdee, 2000.0);
+        assert_eq!(estimate.logged_days, 21);
+        assert_eq!(estimate.partial_days, 7);
+    }
+
+    #[test]
+    fn confidence_bands() {
+        let stable = |every| weigh_ins(28, every, 0.0);
+        // 28 of 28 days and 28 weigh-ins
+        assert_eq!(estimate_from(&days(28, |_| 2000.0), &stable(1), 28).unwrap().confidence, Confidence::High);
+        // 28 of 28 days but only 4 weigh-ins
+        assert_eq!(estimate_from(&days(28, |_| 2000.0), &stable(7), 28).unwrap().confidence, Confidence::Medium);
+        // 14 of 28 days
+        let half = days(28, |day| if day % 2 == 0 { 2000.0 } else { 0.0 });
+        assert_eq!(estimate_from(&half, &stable(1), 28).unwrap().confidence, Confidence::Medium);
+        // 7 of 28 days
+        let quarter = days(28, |day| if day % 4 == 0 { 2000.0 } else { 0.0 });
+        assert_eq!(estimate_from(&quarter, &stable(1), 28).unwrap().confidence, Confidence::Low);
+        // Partly logged days do not count towards coverage
+        let partial = days(28, |day| if day % 2 == 0 { 2000.0 } else { 300.0 });
+        assert_eq!(estimate_from(&partial, &stable(1), 28).unwrap().confidence, Confidence::Medium);
+    }
+
+    #[test]
+    fn not_enough_data() {
+        // Six fully logged days
+        let short = days(28, |day| if day < 6 { 2000.0 } else { 0.0 });
+        assert!(estimate_from(&short, &weigh_ins(28, 1, 0.0), 28).is_none());
+        // Weigh-ins less than a week apart
+        assert!(estimate_from(&days(28, |_| 2000.0), &weigh_ins(6, 1, 0.0), 28).is_none());
+        assert!(estimate_from(&days(28, |_| 2000.0), &[], 28).is_none());
+    }
+}
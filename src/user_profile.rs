@@ -1,10 +1,19 @@
 use serde::{Serialize, Deserialize};
-use std::path::Path;
-use std::fs::{self, File};
+use std::fs::File;
 use std::io;
 use std::io::Write;
+use chrono::{Datelike, NaiveDate, Weekday};
 
-#[derive(Serialize, Deserialize, Debug, Clone)]
+use crate::schema::{self, SchemaKind};
+use crate::food_database::{Allergen, DietaryInfo, Macros};
+use crate::food_log::{self, Meal};
+use crate::weight_journal::WeightJournal;
+use crate::goal::{GoalPlan, WeightGoal, minimum_calories};
+use crate::calorie_strategy::{StrategyInput, TargetCalorieCalcStrategy};
+use crate::calorie_budget::WeeklyCalorieBudget;
+use crate::input::{self, InputError};
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
 pub enum ActivityLevel {
     Sedentary,
     LightlyActive,
@@ -13,34 +22,430 @@ pub enum ActivityLevel {
     SuperActive,
 }
 
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub enum Gender {
+    Male,
+    Female,
+}
+
+// Body-fat percentage from the US Navy circumference method. All
+// measurements are in cm; `hip` is only used for women. Returns None if the
+// measurements cannot give a sensible result.
+pub fn us_navy_body_fat(gender: &Gender, height: f64, neck: f64, waist: f64, hip: Option<f64>) -> Option<f64> {
+    let percentage = match gender {
+        Gender::Male => {
+            if waist <= neck {
+                return None;
+            }
+            495.0 / (1.0324 - 0.19077 * (waist - neck).log10() + 0.15456 * height.log10()) - 450.0
+        }
+        Gender::Female => {
+            let hip = hip?;
+            if waist + hip <= neck {
+                return None;
+            }
+            495.0 / (1.29579 - 0.35004 * (waist + hip - neck).log10() + 0.22100 * height.log10()) - 450.0
+        }
+    };
+    (percentage > 2.0 && percentage < 70.0).then_some((percentage * 10.0).round() / 10.0)
+}
+
+// Foods a user needs to avoid
+#[derive(Serialize, Deserialize, Debug, Clone, Default)]
+pub struct DietaryRestrictions {
+    #[serde(default)]
+    pub avoid_allergens: Vec<Allergen>,
+    #[serde(default)]
+    pub vegan: bool,
+    #[serde(default)]
+    pub vegetarian: bool,
+    #[serde(default)]
+    pub halal: bool,
+    #[serde(default)]
+    pub gluten_free: bool,
+}
+
+impl DietaryRestrictions {
+    pub fn is_empty(&self) -> bool {
+        self.avoid_allergens.is_empty() && !self.vegan && !self.vegetarian && !self.halal && !self.gluten_free
+    }
+
+    // List the ways a food breaks these restrictions (empty if it is fine)
+    pub fn violations(&self, info: &DietaryInfo) -> Vec<String> {
+        let mut violations: Vec<String> = self.avoid_allergens.iter()
+            .filter(|a| info.allergens.contains(a))
+            .map(|a| format!("contains {}", a.name()))
+            .collect();
+
+        if self.vegan && info.vegan == Some(false) {
+            violations.push("not vegan".to_string());
+        }
+        if self.vegetarian && info.vegetarian == Some(false) {
+            violations.push("not vegetarian".to_string());
+        }
+        if self.halal && info.halal == Some(false) {
+            violations.push("not halal".to_string());
+        }
+        if self.gluten_free && info.is_gluten_free() == Some(false) {
+            violations.push("not gluten-free".to_string());
+        }
+
+        violations
+    }
+
+    // The restrictions a food has no recorded answer for. Such foods are
+    // still shown, but flagged so the user can check them.
+    pub fn unknowns(&self, info: &DietaryInfo) -> Vec<&'static str> {
+        let mut unknowns = Vec::new();
+        if self.vegan && info.vegan.is_none() {
+            unknowns.push("vegan");
+        }
+        if self.vegetarian && info.vegetarian.is_none() {
+            unknowns.push("vegetarian");
+        }
+        if self.halal && info.halal.is_none() {
+            unknowns.push("halal");
+        }
+        if self.gluten_free && info.is_gluten_free().is_none() {
+            unknowns.push("gluten-free");
+        }
+        unknowns
+    }
+
+    pub fn describe(&self) -> String {
+        if self.is_empty() {
+            return "none".to_string();
+        }
+
+        let mut parts = Vec::new();
+        if self.vegan { parts.push("vegan".to_string()); }
+        if self.vegetarian { parts.push("vegetarian".to_string()); }
+        if self.halal { parts.push("halal".to_string()); }
+        if self.gluten_free { parts.push("gluten-free".to_string()); }
+        for allergen in &self.avoid_allergens {
+            parts.push(format!("no {}", allergen.name()));
+        }
+        parts.join(", ")
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub enum BudgetPeriod {
+    Daily,
+    Weekly,
+}
+
+// How much the user wants to spend on food
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct FoodBudget {
+    pub amount: f64,
+    pub currency: String,
+    pub period: BudgetPeriod,
+}
+
+impl FoodBudget {
+    pub fn daily_amount(&self) -> f64 {
+        match self.period {
+            BudgetPeriod::Daily => self.amount,
+            BudgetPeriod::Weekly => self.amount / 7.0,
+        }
+    }
+
+    pub fn weekly_amount(&self) -> f64 {
+        match self.period {
+            BudgetPeriod::Daily => self.amount * 7.0,
+            BudgetPeriod::Weekly => self.amount,
+        }
+    }
+}
+
+// Daily glycemic load bands: at or below `low` is low, at or above `high` is high
 #[derive(Serialize, Deserialize, Debug, Clone)]
-pub enum TargetCalorieCalcStrategy {
-    MifflinStJeor,
-    KatchMcArdle,
-    HarrisBenedict,
+pub struct GlycemicThresholds {
+    pub low: f64,
+    pub high: f64,
+}
+
+impl Default for GlycemicThresholds {
+    fn default() -> Self {
+        GlycemicThresholds { low: 80.0, high: 120.0 }
+    }
+}
+
+impl GlycemicThresholds {
+    // Rate a day's load; None means there was no GI data to rate it on
+    pub fn classify(&self, load: Option<f64>) -> &'static str {
+        let Some(load) = load else {
+            return "Unknown";
+        };
+        if load <= self.low {
+            "Low"
+        } else if load >= self.high {
+            "High"
+        } else {
+            "Medium"
+        }
+    }
 }
 
+// Share of the daily calorie target for each standard meal, in percent
 #[derive(Serialize, Deserialize, Debug, Clone)]
-pub enum Gender {
-    Male,
-    Female,
+pub struct MealSplit {
+    pub breakfast: f64,
+    pub lunch: f64,
+    pub dinner: f64,
+    pub snack: f64,
+}
+
+impl Default for MealSplit {
+    fn default() -> Self {
+        MealSplit { breakfast: 25.0, lunch: 35.0, dinner: 30.0, snack: 10.0 }
+    }
+}
+
+impl MealSplit {
+    // Calorie target for a meal; custom and unassigned entries have no share of their own
+    pub fn target_for(&self, meal: &Meal, daily_target: f64) -> Option<f64> {
+        let percent = match meal {
+            Meal::Breakfast => self.breakfast,
+            Meal::Lunch => self.lunch,
+            Meal::Dinner => self.dinner,
+            Meal::Snack => self.snack,
+            Meal::Custom(_) | Meal::Unassigned => return None,
+        };
+        Some(daily_target * percent / 100.0)
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub enum MacroBasis {
+    GramsPerKg,        // Grams of each macro per kg of body weight
+    PercentOfCalories, // Share of the daily calorie target
+}
+
+// Daily protein, carbohydrate and fat goals
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct MacroTargets {
+    pub basis: MacroBasis,
+    pub protein: f64,
+    pub carbs: f64,
+    pub fat: f64,
+}
+
+impl MacroTargets {
+    pub const PRESETS: [(&'static str, MacroTargets); 3] = [
+        ("Balanced", MacroTargets { basis: MacroBasis::PercentOfCalories, protein: 20.0, carbs: 50.0, fat: 30.0 }),
+        ("High protein", MacroTargets { basis: MacroBasis::PercentOfCalories, protein: 35.0, carbs: 35.0, fat: 30.0 }),
+        ("Keto", MacroTargets { basis: MacroBasis::PercentOfCalories, protein: 20.0, carbs: 5.0, fat: 75.0 }),
+    ];
+
+    // Grams of each macro for a body weight and daily calorie target
+    pub fn grams(&self, weight: f64, calorie_target: f64) -> Macros {
+        match self.basis {
+            MacroBasis::GramsPerKg => Macros {
+                protein: self.protein * weight,
+                carbs: self.carbs * weight,
+                fat: self.fat * weight,
+            },
+            MacroBasis::PercentOfCalories => Macros {
+                protein: calorie_target * self.protein / 100.0 / 4.0,
+                carbs: calorie_target * self.carbs / 100.0 / 4.0,
+                fat: calorie_target * self.fat / 100.0 / 9.0,
+            },
+        }
+    }
+
+    pub fn describe(&self) -> String {
+        let preset = Self::PRESETS.iter().find(|(_, preset)| preset == self);
+        let values = match self.basis {
+            MacroBasis::GramsPerKg => format!("protein {:.1}, carbs {:.1}, fat {:.1} g/kg", self.protein, self.carbs, self.fat),
+            MacroBasis::PercentOfCalories => format!("protein {:.0}%, carbs {:.0}%, fat {:.0}% of calories", self.protein, self.carbs, self.fat),
+        };
+        match preset {
+            Some((name, _)) => format!("{} ({})", name, values),
+            None => values,
+        }
+    }
+}
+
+// Calorie cycling: how the daily target is spread across the week. The
+// weekly average always stays at the base target.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub enum CalorieCycle {
+    // Training days get `training_adjustment` percent more than the base
+    // target and rest days share the cut that pays for it
+    DayTypes {
+        training_days: Vec<String>, // Weekday names, e.g. "Mon"
+        training_adjustment: f64,
+        #[serde(default)]
+        training_macros: Option<MacroTargets>,
+        #[serde(default)]
+        rest_macros: Option<MacroTargets>,
+    },
+    // Percent adjustment for each weekday, Monday first, averaging zero
+    Weekdays {
+        adjustments: [f64; 7],
+    },
+}
+
+const WEEK: [Weekday; 7] = [Weekday::Mon, Weekday::Tue, Weekday::Wed, Weekday::Thu, Weekday::Fri, Weekday::Sat, Weekday::Sun];
+
+// No cycled day may drop below this share of the base target
+const MIN_CYCLE_FACTOR: f64 = 0.5;
+
+impl CalorieCycle {
+    // Shift weekday adjustments so they average zero
+    pub fn weekdays(adjustments: [f64; 7]) -> CalorieCycle {
+        let mean = adjustments.iter().sum::<f64>() / 7.0;
+        CalorieCycle::Weekdays { adjustments: adjustments.map(|adjustment| adjustment - mean) }
+    }
+
+    fn is_training_day(training_days: &[String], weekday: Weekday) -> bool {
+        training_days.iter().any(|day| day.parse::<Weekday>() == Ok(weekday))
+    }
+
+    // Multiplier on the base target for a weekday
+    pub fn factor(&self, weekday: Weekday) -> f64 {
+        match self {
+            CalorieCycle::DayTypes { training_days, training_adjustment, .. } => {
+                let training_count = training_days.len();
+                if training_count == 0 || training_count >= 7 {
+                    return 1.0;
+                }
+                if Self::is_training_day(training_days, weekday) {
+                    1.0 + training_adjustment / 100.0
+                } else {
+                    1.0 - training_adjustment / 100.0 * training_count as f64 / (7 - training_count) as f64
+                }
+            }
+            CalorieCycle::Weekdays { adjustments } => {
+                1.0 + adjustments[weekday.num_days_from_monday() as usize] / 100.0
+            }
+        }
+    }
+
+    // Smallest multiplier across the week
+    pub fn lowest_factor(&self) -> f64 {
+        WEEK.into_iter().map(|weekday| self.factor(weekday)).fold(f64::INFINITY, f64::min)
+    }
+
+    // Macro targets for the day type, if they differ from the usual ones
+    pub fn macro_targets(&self, weekday: Weekday) -> Option<&MacroTargets> {
+        match self {
+            CalorieCycle::DayTypes { training_days, training_macros, rest_macros, .. } => {
+                if Self::is_training_day(training_days, weekday) {
+                    training_macros.as_ref()
+                } else {
+                    rest_macros.as_ref()
+                }
+            }
+            CalorieCycle::Weekdays { .. } => None,
+        }
+    }
+
+    // "training day" or "rest day" when cycling by day type
+    pub fn day_label(&self, weekday: Weekday) -> Option<&'static str> {
+        match self {
+            CalorieCycle::DayTypes { training_days, .. } if Self::is_training_day(training_days, weekday) => Some("training day"),
+            CalorieCycle::DayTypes { .. } => Some("rest day"),
+            CalorieCycle::Weekdays { .. } => None,
+        }
+    }
+
+    pub fn describe(&self) -> String {
+        match self {
+            CalorieCycle::DayTypes { training_days, training_adjustment, .. } => {
+                format!("training days {} at {:+.0}%, rest days {:+.0}%",
+                    training_days.join(", "),
+                    training_adjustment,
+                    (self.factor(Self::first_rest_day(training_days)) - 1.0) * 100.0)
+            }
+            CalorieCycle::Weekdays { adjustments } => {
+                const DAYS: [&str; 7] = ["Mon", "Tue", "Wed", "Thu", "Fri", "Sat", "Sun"];
+                DAYS.iter().zip(adjustments)
+                    .map(|(day, adjustment)| format!("{} {:+.0}%", day, adjustment))
+                    .collect::<Vec<_>>()
+                    .join(", ")
+            }
+        }
+    }
+
+    fn first_rest_day(training_days: &[String]) -> Weekday {
+        WEEK.into_iter()
+            .find(|weekday| !Self::is_training_day(training_days, *weekday))
+            .unwrap_or(Weekday::Mon)
+    }
+}
+
+// The daily target in effect from a date on, with the profile values and
+// strategy it was calculated from
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct TargetSnapshot {
+    pub effective_date: String, // YYYY-MM-DD
+    pub target_calorie: f64,
+    pub strategy: TargetCalorieCalcStrategy,
+    pub height: f64,
+    pub weight: f64,
+    pub age: u32,
+    pub gender: Gender,
+    pub activity_level: ActivityLevel,
+    #[serde(default)]
+    pub goal: Option<WeightGoal>,
+    #[serde(default)]
+    pub body_fat_percentage: Option<f64>,
+    #[serde(default)]
+    pub calorie_cycle: Option<CalorieCycle>,
+    #[serde(default)]
+    pub macro_targets: Option<MacroTargets>,
+}
+
+// A new ID for a user, not taken by any of `users` and with no log file
+// left behind under it
+pub fn generate_user_id(users: &[UserProfile]) -> String {
+    let nanos = chrono::Utc::now().timestamp_nanos_opt().unwrap_or_default() as u64;
+    (0..)
+        .map(|salt| format!("u{:x}", nanos.wrapping_add(salt)))
+        .find(|id| !users.iter().any(|user| user.id == *id) && !food_log::log_exists(id))
+        .unwrap_or_default()
 }
 
 #[derive(Serialize, Deserialize, Debug)]
 pub struct UserProfile {
+    pub id: String, // Stable across renames; names the user's log file
     pub name: String,
     pub height: f64,
-    pub weight: f64,
+    pub weight: f64, // Latest trend weight once the weight journal has entries
     pub age: u32,
-    #[serde(default = "default_gender")]
     pub gender: Gender,
     pub activity_level: ActivityLevel,
     pub target_calorie_calc_strategy: TargetCalorieCalcStrategy,
-    pub target_calorie: f64,
-}
-
-fn default_gender() -> Gender {
-    Gender::Male // Default to Male when gender field is missing
+    pub target_calorie: f64, // Maintenance, adjusted for the weight goal if there is one
+    #[serde(default)]
+    pub dietary_restrictions: DietaryRestrictions,
+    #[serde(default)]
+    pub food_budget: Option<FoodBudget>,
+    #[serde(default)]
+    pub glycemic_thresholds: GlycemicThresholds,
+    #[serde(default)]
+    pub meal_split: MealSplit,
+    #[serde(default)]
+    pub weight_journal: WeightJournal,
+    #[serde(default)]
+    pub target_history: Vec<TargetSnapshot>, // Oldest first
+    #[serde(default)]
+    pub adaptive_tdee: Option<f64>, // Last adopted adaptive maintenance estimate
+    #[serde(default)]
+    pub goal: Option<WeightGoal>,
+    #[serde(default)]
+    pub body_fat_percentage: Option<f64>, // Measured or estimated; used by lean-mass strategies
+    #[serde(default)]
+    pub fixed_target: Option<f64>, // Daily target for the Fixed strategy
+    #[serde(default)]
+    pub macro_targets: Option<MacroTargets>,
+    #[serde(default)]
+    pub calorie_cycle: Option<CalorieCycle>, // Spreads `target_calorie` unevenly across the week
+    #[serde(default)]
+    pub calorie_budget: Option<WeeklyCalorieBudget>, // Weekly budget mode with banking when set
 }
 
 impl UserProfile {
@@ -53,16 +458,13 @@ impl UserProfile {
         activity_level: ActivityLevel,
         target_calorie_calc_strategy: TargetCalorieCalcStrategy,
     ) -> Self {
-        let target_calorie = Self::calculate_target_calorie(
-            height,
-            weight,
-            age,
-            &gender,
-            &activity_level,
-            &target_calorie_calc_strategy,
-        );
+        // Start the journal with the weight given at sign-up
+        let mut weight_journal = WeightJournal::default();
+        let today = chrono::Local::now().format("%Y-%m-%d").to_string();
+        let _ = weight_journal.record(&today, weight);
         
-        UserProfile {
+        let mut user = UserProfile {
+            id: generate_user_id(&[]),
             name,
             height,
             weight,
@@ -70,161 +472,312 @@ impl UserProfile {
             gender,
             activity_level,
             target_calorie_calc_strategy,
-            target_calorie,
+            target_calorie: 0.0,
+            dietary_restrictions: DietaryRestrictions::default(),
+            food_budget: None,
+            glycemic_thresholds: GlycemicThresholds::default(),
+            meal_split: MealSplit::default(),
+            weight_journal,
+            target_history: Vec::new(),
+            adaptive_tdee: None,
+            goal: None,
+            body_fat_percentage: None,
+            fixed_target: None,
+            macro_targets: None,
+            calorie_cycle: None,
+            calorie_budget: None,
+        };
+        user.recalculate_target();
+        user
+    }
+    
+    // Record a weigh-in. The profile weight follows the smoothed trend and
+    // the target is recalculated from it.
+    pub fn record_weight(&mut self, date: &str, weight: f64) -> Result<(), io::Error> {
+        self.weight_journal.record(date, weight)?;
+        self.update_weight_from_journal();
+        Ok(())
+    }
+    
+    pub fn remove_weight(&mut self, date: &str) -> Result<(), io::Error> {
+        self.weight_journal.remove(date)?;
+        self.update_weight_from_journal();
+        Ok(())
+    }
+    
+    fn update_weight_from_journal(&mut self) {
+        if let Some(trend) = self.weight_journal.latest_trend() {
+            self.weight = (trend * 10.0).round() / 10.0;
         }
+        self.recalculate_target();
     }
     
-    pub fn calculate_target_calorie(
-        height: f64,
-        weight: f64,
-        age: u32,
-        gender: &Gender,
-        activity_level: &ActivityLevel,
-        strategy: &TargetCalorieCalcStrategy,
-    ) -> f64 {
-        // Calculate BMR based on strategy
-        let bmr = match strategy {
-            TargetCalorieCalcStrategy::MifflinStJeor => {
-                let base = 10.0 * weight + 6.25 * height - 5.0 * age as f64;
-                match gender {
-                    Gender::Male => base + 5.0,
-                    Gender::Female => base - 161.0,
-                }
-            },
-            TargetCalorieCalcStrategy::KatchMcArdle => {
-                // Estimate lean body mass (simplified as we don't have body fat %)
-                let estimated_body_fat_percentage = match gender {
-                    Gender::Male => 15.0,  // Rough average for men
-                    Gender::Female => 25.0,  // Rough average for women
-                };
-                
-                let lean_body_mass = weight * (100.0 - estimated_body_fat_percentage) / 100.0;
-                370.0 + (21.6 * lean_body_mass)
-            },
-            TargetCalorieCalcStrategy::HarrisBenedict => {
-                match gender {
-                    Gender::Male => {
-                        66.0 + (13.75 * weight) + (5.0 * height) - (6.76 * age as f64)
-                    },
-                    Gender::Female => {
-                        655.0 + (9.56 * weight) + (1.85 * height) - (4.68 * age as f64)
-                    },
-                }
-            },
+    pub fn strategy_input(&self) -> StrategyInput<'_> {
+        StrategyInput {
+            height: self.height,
+            weight: self.weight,
+            age: self.age,
+            gender: &self.gender,
+            activity_level: &self.activity_level,
+            body_fat_percentage: self.body_fat_percentage,
+            adaptive_tdee: self.adaptive_tdee,
+            fixed_target: self.fixed_target,
+        }
+    }
+    
+    // Calories needed to keep the current weight. Strategies missing the data
+    // they need (an adopted estimate, a fixed target) fall back to Mifflin-St Jeor.
+    pub fn maintenance_calories(&self) -> f64 {
+        let input = self.strategy_input();
+        self.target_calorie_calc_strategy.maintenance(&input)
+            .or_else(|| TargetCalorieCalcStrategy::MIFFLIN_ST_JEOR.maintenance(&input))
+            .unwrap_or(self.target_calorie)
+    }
+    
+    // True when the strategy needs lean body mass but no body-fat percentage
+    // is known, so a population average is being assumed
+    pub fn missing_body_fat(&self) -> bool {
+        self.target_calorie_calc_strategy.uses_lean_mass() && self.body_fat_percentage.is_none()
+    }
+    
+    // How the weight goal adjusts maintenance, if there is a goal and the
+    // strategy takes one into account
+    pub fn goal_plan(&self) -> Option<GoalPlan> {
+        if !self.target_calorie_calc_strategy.applies_goal() {
+            return None;
+        }
+        let today = chrono::Local::now().date_naive();
+        self.goal.as_ref()
+            .map(|goal| goal.plan(self.weight, self.maintenance_calories(), &self.gender, today))
+    }
+    
+    pub fn set_goal(&mut self, goal: Option<WeightGoal>) {
+        self.goal = goal;
+        self.recalculate_target();
+    }
+    
+    pub fn recalculate_target(&mut self) {
+        self.target_calorie = match self.goal_plan() {
+            Some(plan) => plan.target,
+            None => self.maintenance_calories(),
         };
-        
-        // Apply activity level multiplier
-        let activity_multiplier = match activity_level {
-            ActivityLevel::Sedentary => 1.2,
-            ActivityLevel::LightlyActive => 1.375,
-            ActivityLevel::ModeratelyActive => 1.55,
-            ActivityLevel::VeryActive => 1.725,
-            ActivityLevel::SuperActive => 1.9,
+        // A lower target can push a cycled day too far down, so drop the
+        // cycle rather than let any day go below the limits
+        if let Some(cycle) = &self.calorie_cycle
+            && let Err(e) = check_cycle_days(cycle, self.target_calorie, &self.gender) {
+            println!("{} Calorie cycling has been turned off.", e);
+            self.calorie_cycle = None;
+        }
+        self.snapshot_target();
+    }
+    
+    // Use an adaptive maintenance estimate as the daily target
+    pub fn adopt_adaptive_target(&mut self, tdee: f64) {
+        self.adaptive_tdee = Some(tdee);
+        self.target_calorie_calc_strategy = TargetCalorieCalcStrategy::ADAPTIVE;
+        self.recalculate_target();
+    }
+    
+    // Record the current target in the history if anything behind it changed.
+    // Several changes on one day keep only the last.
+    fn snapshot_target(&mut self) {
+        let today = chrono::Local::now().format("%Y-%m-%d").to_string();
+        let snapshot = TargetSnapshot {
+            effective_date: today.clone(),
+            target_calorie: self.target_calorie,
+            strategy: self.target_calorie_calc_strategy,
+            height: self.height,
+            weight: self.weight,
+            age: self.age,
+            gender: self.gender.clone(),
+            activity_level: self.activity_level.clone(),
+            goal: self.goal.clone(),
+            body_fat_percentage: self.body_fat_percentage,
+            calorie_cycle: self.calorie_cycle.clone(),
+            macro_targets: self.macro_targets.clone(),
         };
         
-        (bmr * activity_multiplier).round() // Rounded to the nearest whole calorie
+        if let Some(last) = self.target_history.last_mut() {
+            let unchanged = TargetSnapshot { effective_date: last.effective_date.clone(), ..snapshot.clone() } == *last;
+            if unchanged {
+                return;
+            }
+            if last.effective_date == today {
+                *last = snapshot;
+                return;
+            }
+        }
+        self.target_history.push(snapshot);
+    }
+    
+    // Macro goals in grams for a date, from the macro targets in effect then,
+    // that day's calorie target and trend weight. Uses the day type's macro
+    // targets when calorie cycling sets them.
+    pub fn macro_targets_for_date(&self, date: &str) -> Option<Macros> {
+        let weight = self.weight_journal.trend_on(date).unwrap_or(self.weight);
+        let cycled = match (self.cycle_for_date(date), NaiveDate::parse_from_str(date, "%Y-%m-%d")) {
+            (Some(cycle), Ok(day)) => cycle.macro_targets(day.weekday()),
+            _ => None,
+        };
+        let in_effect = match self.snapshot_for_date(date) {
+            Some(snapshot) => snapshot.macro_targets.as_ref(),
+            None => self.macro_targets.as_ref(),
+        };
+        cycled.or(in_effect)
+            .map(|targets| targets.grams(weight, self.target_for_date(date)))
+    }
+    
+    // The snapshot in effect on a date. Dates before the history starts use
+    // the earliest one.
+    fn snapshot_for_date(&self, date: &str) -> Option<&TargetSnapshot> {
+        self.target_history.iter()
+            .rev()
+            .find(|snapshot| snapshot.effective_date.as_str() <= date)
+            .or(self.target_history.first())
+    }
+    
+    fn cycle_for_date(&self, date: &str) -> Option<&CalorieCycle> {
+        self.snapshot_for_date(date).and_then(|snapshot| snapshot.calorie_cycle.as_ref())
+    }
+    
+    // The target that was in effect on a date, adjusted for that day of the
+    // week if calorie cycling was on
+    pub fn target_for_date(&self, date: &str) -> f64 {
+        let Some(snapshot) = self.snapshot_for_date(date) else {
+            return self.target_calorie;
+        };
+        match (&snapshot.calorie_cycle, NaiveDate::parse_from_str(date, "%Y-%m-%d")) {
+            (Some(cycle), Ok(day)) => (snapshot.target_calorie * cycle.factor(day.weekday())).round(),
+            _ => snapshot.target_calorie,
+        }
+    }
+    
+    // "training day" or "rest day" for a date when cycling by day type
+    pub fn day_label_for_date(&self, date: &str) -> Option<&'static str> {
+        let day = NaiveDate::parse_from_str(date, "%Y-%m-%d").ok()?;
+        self.cycle_for_date(date)?.day_label(day.weekday())
     }
 }
 
-pub fn load_users() -> Vec<UserProfile> {
+// On-disk layout of users.yaml
+#[derive(Serialize, Deserialize)]
+struct UsersWrapper {
+    users: Vec<UserProfile>,
+}
+
+#[derive(Serialize)]
+struct UsersWrapperRef<'a> {
+    users: &'a [UserProfile],
+}
+
+pub fn load_users() -> Result<Vec<UserProfile>, io::Error> {
     let file_path = "users.yaml";
-    if Path::new(file_path).exists() {
-        let data = fs::read_to_string(file_path).expect("Unable to read file");
-        serde_yaml::from_str(&data).expect("Unable to parse YAML")
-    } else {
-        Vec::new()
+    let wrapper = schema::load_versioned::<UsersWrapper>(file_path, SchemaKind::Users)?;
+    Ok(wrapper.map(|w| w.users).unwrap_or_default())
+}
+
+// Check a new name for a user: not empty and not already taken by someone else
+pub fn validate_user_name(users: &[UserProfile], name: &str, except_id: Option<&str>) -> Result<(), io::Error> {
+    if name.is_empty() {
+        return Err(io::Error::new(io::ErrorKind::InvalidInput, "Name cannot be empty"));
+    }
+    if users.iter().any(|user| user.name.eq_ignore_ascii_case(name) && Some(user.id.as_str()) != except_id) {
+        return Err(io::Error::new(
+            io::ErrorKind::AlreadyExists,
+            format!("A user named '{}' already exists", name)
+        ));
     }
+    Ok(())
+}
+
+pub fn rename_user(users: &mut [UserProfile], index: usize, new_name: &str) -> Result<(), io::Error> {
+    let new_name = new_name.trim();
+    validate_user_name(users, new_name, Some(&users[index].id))?;
+    users[index].name = new_name.to_string();
+    Ok(())
 }
 
-pub fn save_users(users: &Vec<UserProfile>) {
+// Remove a user, moving their food log into the archive. Returns the removed
+// profile and where the log was archived, if they had one.
+pub fn delete_user(users: &mut Vec<UserProfile>, index: usize) -> Result<(UserProfile, Option<String>), io::Error> {
+    let archived = food_log::archive_log(&users[index].id)?;
+    Ok((users.remove(index), archived))
+}
+
+pub fn save_users(users: &[UserProfile]) {
     let file_path = "users.yaml";
-    let data = serde_yaml::to_string(users).expect("Unable to serialize users");
+    let data = schema::to_versioned_yaml(&UsersWrapperRef { users }, SchemaKind::Users)
+        .expect("Unable to serialize users");
     let mut file = File::create(file_path).expect("Unable to create file");
     file.write_all(data.as_bytes()).expect("Unable to write data");
 }
 
-pub fn select_user(users: &Vec<UserProfile>) -> Option<usize> {
+// Returns None if no user was picked
+pub fn select_user(users: &[UserProfile]) -> Result<Option<usize>, InputError> {
     println!("Select a user:");
     for (i, user) in users.iter().enumerate() {
         println!("{}: {}", i + 1, user.name);
     }
-    println!("Enter the number of the user (or 0 to cancel):");
 
-    let mut input = String::new();
-    io::stdin().read_line(&mut input).expect("Failed to read input");
-    if let Ok(choice) = input.trim().parse::<usize>() {
-        if choice > 0 && choice <= users.len() {
-            return Some(choice - 1);
-        }
+    let input = match input::read_line("Enter the number of the user (or 0 to cancel):") {
+        Ok(input) => input,
+        Err(InputError::Cancelled) => return Ok(None),
+        Err(e) => return Err(e),
+    };
+    if let Ok(choice) = input.parse::<usize>()
+        && choice > 0 && choice <= users.len() {
+        return Ok(Some(choice - 1));
     }
-    None
+    Ok(None)
 }
 
-pub fn create_user() -> UserProfile {
-    println!("Creating a new user:");
-    let mut input = String::new();
-
-    println!("Enter name:");
-    io::stdin().read_line(&mut input).expect("Failed to read input");
-    let name = input.trim().to_string();
+// Ask for a new user's details. Bad input is asked for again; typing the
+// cancel word at any prompt returns `InputError::Cancelled`.
+pub fn create_user(users: &[UserProfile]) -> Result<UserProfile, InputError> {
+    println!("Creating a new user (type '{}' at any prompt to go back):", input::CANCEL_WORD);
 
-    println!("Enter height (in cm):");
-    input.clear();
-    io::stdin().read_line(&mut input).expect("Failed to read input");
-    let height: f64 = input.trim().parse().expect("Invalid height");
+    let name = loop {
+        let name = input::read_text("Enter name:")?;
+        match validate_user_name(users, &name, None) {
+            Ok(_) => break name,
+            Err(e) => println!("{}. Please enter another name.", e),
+        }
+    };
+    let height = input::read_number("Enter height (in cm):", input::HEIGHT_RANGE)?;
+    let weight = input::read_number("Enter weight (in kg):", input::WEIGHT_RANGE)?;
+    let age = input::read_number("Enter age:", input::AGE_RANGE)?;
 
-    println!("Enter weight (in kg):");
-    input.clear();
-    io::stdin().read_line(&mut input).expect("Failed to read input");
-    let weight: f64 = input.trim().parse().expect("Invalid weight");
+    let gender = input::read_choice("Select gender:", &[
+        ("Male", Gender::Male),
+        ("Female", Gender::Female),
+    ])?;
 
-    println!("Enter age:");
-    input.clear();
-    io::stdin().read_line(&mut input).expect("Failed to read input");
-    let age: u32 = input.trim().parse().expect("Invalid age");
-    
-    println!("Select gender:");
-    println!("1: Male");
-    println!("2: Female");
-    input.clear();
-    io::stdin().read_line(&mut input).expect("Failed to read input");
-    let gender = match input.trim() {
-        "1" => Gender::Male,
-        "2" => Gender::Female,
-        _ => panic!("Invalid choice"),
-    };
+    let activity_level = input::read_choice("Select activity level:", &[
+        ("Sedentary", ActivityLevel::Sedentary),
+        ("Lightly Active", ActivityLevel::LightlyActive),
+        ("Moderately Active", ActivityLevel::ModeratelyActive),
+        ("Very Active", ActivityLevel::VeryActive),
+        ("Super Active", ActivityLevel::SuperActive),
+    ])?;
 
-    println!("Select activity level:");
-    println!("1: Sedentary");
-    println!("2: Lightly Active");
-    println!("3: Moderately Active");
-    println!("4: Very Active");
-    println!("5: Super Active");
-    input.clear();
-    io::stdin().read_line(&mut input).expect("Failed to read input");
-    let activity_level = match input.trim() {
-        "1" => ActivityLevel::Sedentary,
-        "2" => ActivityLevel::LightlyActive,
-        "3" => ActivityLevel::ModeratelyActive,
-        "4" => ActivityLevel::VeryActive,
-        "5" => ActivityLevel::SuperActive,
-        _ => panic!("Invalid choice"),
+    // A new user has no logged data to base an adaptive estimate on
+    let strategies: Vec<(&str, TargetCalorieCalcStrategy)> = TargetCalorieCalcStrategy::all()
+        .filter(|strategy| *strategy != TargetCalorieCalcStrategy::ADAPTIVE)
+        .map(|strategy| (strategy.display_name(), strategy))
+        .collect();
+    let target_calorie_calc_strategy = input::read_choice("Select calorie calculation strategy:", &strategies)?;
+    let fixed_target = if target_calorie_calc_strategy == TargetCalorieCalcStrategy::FIXED {
+        Some(input::read_number("Enter your daily calorie target:", FIXED_TARGET_RANGE)?)
+    } else {
+        None
     };
 
-    println!("Select calorie calculation strategy:");
-    println!("1: Mifflin-St Jeor");
-    println!("2: Katch-McArdle");
-    println!("3: Harris-Benedict");
-    input.clear();
-    io::stdin().read_line(&mut input).expect("Failed to read input");
-    let target_calorie_calc_strategy = match input.trim() {
-        "1" => TargetCalorieCalcStrategy::MifflinStJeor,
-        "2" => TargetCalorieCalcStrategy::KatchMcArdle,
-        "3" => TargetCalorieCalcStrategy::HarrisBenedict,
-        _ => panic!("Invalid choice"),
-    };
+    let body_fat_percentage = prompt_body_fat(&gender, height)?;
+    if target_calorie_calc_strategy.uses_lean_mass() && body_fat_percentage.is_none() {
+        warn_missing_body_fat(&gender);
+    }
 
-    UserProfile::new(
+    let mut user = UserProfile::new(
         name,
         height,
         weight,
@@ -232,96 +785,494 @@ pub fn create_user() -> UserProfile {
         gender,
         activity_level,
         target_calorie_calc_strategy,
-    )
+    );
+    user.id = generate_user_id(users);
+    user.body_fat_percentage = body_fat_percentage;
+    user.fixed_target = fixed_target;
+    user.recalculate_target();
+    Ok(user)
 }
 
-pub fn modify_user(user: &mut UserProfile) {
-    println!("Modifying user: {}", user.name);
-    let mut input = String::new();
+// Cancelling keeps the changes made so far. Any other input error is
+// returned once the target is recalculated.
+pub fn modify_user(user: &mut UserProfile) -> Result<(), InputError> {
+    println!("Modifying user: {} (use 'Rename User' in the main menu to change the name)", user.name);
+    println!("Type '{}' at any prompt to stop; changes made up to then are kept.", input::CANCEL_WORD);
+    let result = modify_user_fields(user);
+    if let Err(e) = &result {
+        println!("Stopped modifying user: {}", e);
+    }
 
-    println!("Enter new name (or press Enter to keep current):");
-    io::stdin().read_line(&mut input).expect("Failed to read input");
-    if !input.trim().is_empty() {
-        user.name = input.trim().to_string();
+    // Recalculate target calories based on the updated user information
+    user.recalculate_target();
+    
+    println!("Calculated daily target calories: {:.0}", user.target_calorie);
+    match result {
+        Err(InputError::Cancelled) => Ok(()),
+        result => result,
     }
+}
 
-    println!("Enter new height (or press Enter to keep current):");
-    input.clear();
-    io::stdin().read_line(&mut input).expect("Failed to read input");
-    if let Ok(height) = input.trim().parse::<f64>() {
+fn modify_user_fields(user: &mut UserProfile) -> Result<(), InputError> {
+    if let Some(height) = input::read_optional_number("Enter new height in cm (or press Enter to keep current):", input::HEIGHT_RANGE)? {
         user.height = height;
     }
 
-    println!("Enter new weight (or press Enter to keep current):");
-    input.clear();
-    io::stdin().read_line(&mut input).expect("Failed to read input");
-    if let Ok(weight) = input.trim().parse::<f64>() {
-        user.weight = weight;
+    let prompt = format!("Enter today's weight (or press Enter to keep current trend weight of {:.1} kg):", user.weight);
+    if let Some(weight) = input::read_optional_number(&prompt, input::WEIGHT_RANGE)? {
+        let today = chrono::Local::now().format("%Y-%m-%d").to_string();
+        match user.record_weight(&today, weight) {
+            Ok(_) => println!("Recorded {:.1} kg in the weight journal. Trend weight: {:.1} kg", weight, user.weight),
+            Err(e) => println!("Could not record weight: {}", e),
+        }
     }
 
-    println!("Enter new age (or press Enter to keep current):");
-    input.clear();
-    io::stdin().read_line(&mut input).expect("Failed to read input");
-    if let Ok(age) = input.trim().parse::<u32>() {
+    if let Some(age) = input::read_optional_number("Enter new age (or press Enter to keep current):", input::AGE_RANGE)? {
         user.age = age;
     }
 
-    println!("Select new activity level (or press Enter to keep current):");
-    println!("1: Sedentary");
-    println!("2: Lightly Active");
-    println!("3: Moderately Active");
-    println!("4: Very Active");
-    println!("5: Super Active");
-    input.clear();
-    io::stdin().read_line(&mut input).expect("Failed to read input");
-    if let Ok(choice) = input.trim().parse::<u32>() {
-        user.activity_level = match choice {
-            1 => ActivityLevel::Sedentary,
-            2 => ActivityLevel::LightlyActive,
-            3 => ActivityLevel::ModeratelyActive,
-            4 => ActivityLevel::VeryActive,
-            5 => ActivityLevel::SuperActive,
-            _ => user.activity_level.clone(),
-        };
+    if let Some(activity_level) = input::read_optional_choice("Select new activity level:", &[
+        ("Sedentary", ActivityLevel::Sedentary),
+        ("Lightly Active", ActivityLevel::LightlyActive),
+        ("Moderately Active", ActivityLevel::ModeratelyActive),
+        ("Very Active", ActivityLevel::VeryActive),
+        ("Super Active", ActivityLevel::SuperActive),
+    ])? {
+        user.activity_level = activity_level;
     }
 
-    println!("Select new gender (or press Enter to keep current):");
-    println!("1: Male");
-    println!("2: Female");
-    input.clear();
-    io::stdin().read_line(&mut input).expect("Failed to read input");
-    if let Ok(choice) = input.trim().parse::<u32>() {
-        user.gender = match choice {
-            1 => Gender::Male,
-            2 => Gender::Female,
-            _ => user.gender.clone(),
-        };
+    if let Some(gender) = input::read_optional_choice("Select new gender:", &[
+        ("Male", Gender::Male),
+        ("Female", Gender::Female),
+    ])? {
+        user.gender = gender;
     }
 
-    println!("Select new calorie calculation strategy (or press Enter to keep current):");
-    println!("1: Mifflin-St Jeor");
-    println!("2: Katch-McArdle");
-    println!("3: Harris-Benedict");
-    input.clear();
-    io::stdin().read_line(&mut input).expect("Failed to read input");
-    if let Ok(choice) = input.trim().parse::<u32>() {
-        user.target_calorie_calc_strategy = match choice {
-            1 => TargetCalorieCalcStrategy::MifflinStJeor,
-            2 => TargetCalorieCalcStrategy::KatchMcArdle,
-            3 => TargetCalorieCalcStrategy::HarrisBenedict,
-            _ => user.target_calorie_calc_strategy.clone(),
-        };
+    let strategies: Vec<(&str, TargetCalorieCalcStrategy)> = TargetCalorieCalcStrategy::all()
+        .map(|strategy| (strategy.display_name(), strategy))
+        .collect();
+    let prompt = format!("Select new calorie calculation strategy (current: {}):", user.target_calorie_calc_strategy.display_name());
+    if let Some(strategy) = input::read_optional_choice(&prompt, &strategies)? {
+        if strategy == TargetCalorieCalcStrategy::ADAPTIVE && user.adaptive_tdee.is_none() {
+            println!("No adaptive estimate adopted yet. Use 'Adaptive Maintenance Estimate' in the user session first.");
+        } else if strategy == TargetCalorieCalcStrategy::FIXED {
+            match prompt_fixed_target()? {
+                Some(target) => {
+                    user.fixed_target = Some(target);
+                    user.target_calorie_calc_strategy = strategy;
+                }
+                None => println!("No target entered. Keeping current strategy."),
+            }
+        } else {
+            user.target_calorie_calc_strategy = strategy;
+        }
     }
 
-    // Recalculate target calories based on the updated user information
-    user.target_calorie = UserProfile::calculate_target_calorie(
-        user.height,
-        user.weight,
-        user.age,
-        &user.gender,
-        &user.activity_level,
-        &user.target_calorie_calc_strategy,
-    );
-    
-    println!("Calculated daily target calories: {:.0}", user.target_calorie);
-}
\ No newline at end of file
+    match user.body_fat_percentage {
+        Some(percentage) => println!("Current body fat: {:.1}%", percentage),
+        None => println!("Current body fat: unknown"),
+    }
+    if input::read_yes_no("Update body fat?")? {
+        user.body_fat_percentage = prompt_body_fat(&user.gender, user.height)?;
+    }
+    if user.missing_body_fat() {
+        warn_missing_body_fat(&user.gender);
+    }
+
+    match &user.macro_targets {
+        Some(targets) => println!("Current macro targets: {}", targets.describe()),
+        None => println!("Current macro targets: none"),
+    }
+    user.macro_targets = prompt_macro_targets(user.macro_targets.clone())?;
+
+    match &user.calorie_cycle {
+        Some(cycle) => println!("Current calorie cycling: {}", cycle.describe()),
+        None => println!("Current calorie cycling: none"),
+    }
+    // Checked against the recalculated target once all changes are in
+    user.calorie_cycle = prompt_calorie_cycle(user.calorie_cycle.clone())?;
+
+    match &user.calorie_budget {
+        Some(budget) => println!("Weekly budget mode: on (week starts {}, up to {:.0} calories carried forward)",
+            budget.week_start, budget.bank_cap),
+        None => println!("Weekly budget mode: off"),
+    }
+    let bank_cap = input::read_optional_number(
+        "Enter the most calories to carry forward to later days to use weekly budget mode (0 to turn off, or press Enter to keep current):",
+        BANK_CAP_RANGE,
+    )?;
+    if let Some(bank_cap) = bank_cap {
+        if bank_cap == 0.0 {
+            user.calorie_budget = None;
+        } else {
+            let current_start = user.calorie_budget.as_ref()
+                .map_or(WeeklyCalorieBudget::default().week_start, |budget| budget.week_start.clone());
+            let day = input::read_line(&format!("Enter the day your week starts on (e.g. Mon, or press Enter for {}):", current_start))?;
+            let week_start = match day.as_str() {
+                "" => current_start,
+                day => match day.parse::<Weekday>() {
+                    Ok(weekday) => weekday.to_string(),
+                    Err(_) => {
+                        println!("Unknown day '{}', keeping {}.", day, current_start);
+                        current_start
+                    }
+                },
+            };
+            user.calorie_budget = Some(WeeklyCalorieBudget { week_start, bank_cap });
+        }
+    }
+
+    println!("Current dietary restrictions: {}", user.dietary_restrictions.describe());
+    if input::read_yes_no("Update dietary restrictions?")? {
+        user.dietary_restrictions = prompt_dietary_restrictions()?;
+    }
+
+    match &user.food_budget {
+        Some(budget) => println!("Current food budget: {:.2} {} per {}", budget.amount, budget.currency,
+            if budget.period == BudgetPeriod::Daily { "day" } else { "week" }),
+        None => println!("Current food budget: none"),
+    }
+    let amount = input::read_optional_number("Enter new food budget amount (0 to remove, or press Enter to keep current):", FOOD_BUDGET_RANGE)?;
+    if let Some(amount) = amount {
+        if amount == 0.0 {
+            user.food_budget = None;
+        } else {
+            // Keep the previous currency on Enter; with none to keep, one is required
+            let currency = match user.food_budget.as_ref().map(|budget| budget.currency.clone()) {
+                Some(previous) if !previous.is_empty() => {
+                    let currency = input::read_line(&format!("Enter currency (e.g. USD, or press Enter to keep {}):", previous))?;
+                    if currency.is_empty() { previous } else { currency.to_uppercase() }
+                }
+                _ => input::read_text("Enter currency (e.g. USD):")?.to_uppercase(),
+            };
+
+            let period = input::read_line("Is this budget per day or per week? (d/w):")?;
+            let period = if period.eq_ignore_ascii_case("w") {
+                BudgetPeriod::Weekly
+            } else {
+                BudgetPeriod::Daily
+            };
+
+            user.food_budget = Some(FoodBudget { amount, currency, period });
+        }
+    }
+
+    let thresholds = input::read_line(&format!(
+        "Enter new daily glycemic load thresholds as 'low,high' (current: {:.0},{:.0}, or press Enter to keep current):",
+        user.glycemic_thresholds.low, user.glycemic_thresholds.high))?;
+    if let Some((low, high)) = thresholds.split_once(',') {
+        match (low.trim().parse::<f64>(), high.trim().parse::<f64>()) {
+            (Ok(low), Ok(high)) if low < high => {
+                user.glycemic_thresholds = GlycemicThresholds { low, high };
+            }
+            _ => println!("Invalid thresholds. Keeping current values."),
+        }
+    }
+
+    let split = input::read_line(&format!(
+        "Enter new meal split as 'breakfast,lunch,dinner,snack' percentages (current: {:.0},{:.0},{:.0},{:.0}, or press Enter to keep current):",
+        user.meal_split.breakfast, user.meal_split.lunch, user.meal_split.dinner, user.meal_split.snack))?;
+    if !split.is_empty() {
+        let parts: Vec<Option<f64>> = split.split(',').map(|p| p.trim().parse::<f64>().ok()).collect();
+        match parts.as_slice() {
+            [Some(breakfast), Some(lunch), Some(dinner), Some(snack)]
+                if [breakfast, lunch, dinner, snack].iter().all(|p| **p >= 0.0)
+                    && (breakfast + lunch + dinner + snack - 100.0).abs() < 0.5 => {
+                user.meal_split = MealSplit {
+                    breakfast: *breakfast,
+                    lunch: *lunch,
+                    dinner: *dinner,
+                    snack: *snack,
+                };
+            }
+            _ => println!("Meal split must be four non-negative percentages adding up to 100. Keeping current values."),
+        }
+    }
+
+    Ok(())
+}
+
+// Ask for macro targets: a preset, custom percentages or grams per kg.
+// Keeps `current` if the input is skipped or invalid.
+pub fn prompt_macro_targets(current: Option<MacroTargets>) -> Result<Option<MacroTargets>, InputError> {
+    println!("Select macro targets (or press Enter to keep current):");
+    for (i, (_, preset)) in MacroTargets::PRESETS.iter().enumerate() {
+        println!("{}: {}", i + 1, preset.describe());
+    }
+    let custom_percent = MacroTargets::PRESETS.len() + 1;
+    println!("{}: Custom percentages of calories", custom_percent);
+    println!("{}: Custom grams per kg of body weight", custom_percent + 1);
+    println!("{}: No macro targets", custom_percent + 2);
+    let input = input::read_line("Enter a number:")?;
+
+    let Ok(choice) = input.parse::<usize>() else {
+        return Ok(current);
+    };
+    if choice >= 1 && choice <= MacroTargets::PRESETS.len() {
+        return Ok(Some(MacroTargets::PRESETS[choice - 1].1.clone()));
+    }
+    let basis = match choice {
+        c if c == custom_percent => MacroBasis::PercentOfCalories,
+        c if c == custom_percent + 1 => MacroBasis::GramsPerKg,
+        c if c == custom_percent + 2 => return Ok(None),
+        _ => return Ok(current),
+    };
+
+    let unit = if basis == MacroBasis::GramsPerKg { "g per kg" } else { "%" };
+    let input = input::read_line(&format!("Enter protein, carbs and fat as 'protein,carbs,fat' ({}):", unit))?;
+    let parts: Vec<Option<f64>> = input.split(',').map(|p| p.trim().parse::<f64>().ok()).collect();
+    match parts.as_slice() {
+        [Some(protein), Some(carbs), Some(fat)] if [protein, carbs, fat].iter().all(|v| **v >= 0.0) => {
+            if basis == MacroBasis::PercentOfCalories && (protein + carbs + fat - 100.0).abs() >= 0.5 {
+                println!("Percentages must add up to 100. Keeping current macro targets.");
+                return Ok(current);
+            }
+            Ok(Some(MacroTargets { basis, protein: *protein, carbs: *carbs, fat: *fat }))
+        }
+        _ => {
+            println!("Invalid values. Keeping current macro targets.");
+            Ok(current)
+        }
+    }
+}
+
+// Ask how to cycle calories across the week. Keeps `current` if the input
+// is skipped or invalid.
+pub fn prompt_calorie_cycle(current: Option<CalorieCycle>) -> Result<Option<CalorieCycle>, InputError> {
+    println!("Select calorie cycling (or press Enter to keep current):");
+    println!("1: Training and rest days");
+    println!("2: A different target for each weekday");
+    println!("3: Same target every day");
+    let input = input::read_line("Enter a number:")?;
+
+    match input.as_str() {
+        "1" => {
+            let input = input::read_line("Enter training days ('daily', 'weekdays', 'weekends' or e.g. 'Mon, Wed, Fri'):")?;
+            let training_days = match crate::food_log::RecurrenceRule::parse_weekdays(&input) {
+                Ok(days) if !days.is_empty() && days.len() < 7 => days,
+                Ok(_) => {
+                    println!("Pick between one and six training days. Keeping current calorie cycling.");
+                    return Ok(current);
+                }
+                Err(e) => {
+                    println!("{}. Keeping current calorie cycling.", e);
+                    return Ok(current);
+                }
+            };
+
+            let training_adjustment = input::read_number(
+                "Enter how much more to eat on training days, in percent of your daily target (e.g. 15):",
+                TRAINING_ADJUSTMENT_RANGE,
+            )?;
+
+            let (training_macros, rest_macros) = if input::read_yes_no("Use different macro targets on training and rest days?")? {
+                println!("Training day macro targets:");
+                let training_macros = prompt_macro_targets(None)?;
+                println!("Rest day macro targets:");
+                (training_macros, prompt_macro_targets(None)?)
+            } else {
+                (None, None)
+            };
+
+            Ok(Some(CalorieCycle::DayTypes { training_days, training_adjustment, training_macros, rest_macros }))
+        }
+        "2" => {
+            println!("Enter the percent adjustment for each day, Monday to Sunday, as 7 comma-separated values (e.g. '10,-5,10,-5,10,-10,-10').");
+            let input = input::read_line("They are shifted to average zero so the weekly total stays the same:")?;
+            let values: Vec<Option<f64>> = input.split(',').map(|v| v.trim().parse::<f64>().ok()).collect();
+            match values.iter().copied().collect::<Option<Vec<f64>>>() {
+                Some(values) if values.len() == 7 && values.iter().all(|v| v.abs() <= 50.0) => {
+                    let mut adjustments = [0.0; 7];
+                    adjustments.copy_from_slice(&values);
+                    Ok(Some(CalorieCycle::weekdays(adjustments)))
+                }
+                _ => {
+                    println!("Enter 7 adjustments between -50 and 50%. Keeping current calorie cycling.");
+                    Ok(current)
+                }
+            }
+        }
+        "3" => Ok(None),
+        _ => Ok(current),
+    }
+}
+
+// Refuse cycles that cut any day too deep, either relative to the base
+// target or below the safe minimum
+fn check_cycle_days(cycle: &CalorieCycle, base_target: f64, gender: &Gender) -> Result<(), String> {
+    let lowest_factor = cycle.lowest_factor();
+    if lowest_factor < MIN_CYCLE_FACTOR {
+        return Err(format!("Your lowest day would be {:.0}% of your daily target; it can't go below {:.0}%.",
+            lowest_factor * 100.0, MIN_CYCLE_FACTOR * 100.0));
+    }
+    let minimum = minimum_calories(gender);
+    let lowest = base_target * lowest_factor;
+    if lowest < minimum {
+        return Err(format!("Your lowest day would be {:.0} calories, below the safe minimum of {:.0}.", lowest, minimum));
+    }
+    Ok(())
+}
+
+// How much more to eat on training days, in percent of the daily target
+const TRAINING_ADJUSTMENT_RANGE: std::ops::RangeInclusive<f64> = 1.0..=50.0;
+
+// Calories the weekly budget may carry forward; 0 turns budget mode off
+const BANK_CAP_RANGE: std::ops::RangeInclusive<f64> = 0.0..=5000.0;
+
+// Food budget amounts; 0 removes the budget
+const FOOD_BUDGET_RANGE: std::ops::RangeInclusive<f64> = 0.0..=100000.0;
+
+// Daily targets the Fixed strategy accepts
+const FIXED_TARGET_RANGE: std::ops::RangeInclusive<f64> = 800.0..=10000.0;
+
+fn prompt_fixed_target() -> Result<Option<f64>, InputError> {
+    input::read_optional_number("Enter your daily calorie target (or press Enter to skip):", FIXED_TARGET_RANGE)
+}
+
+fn warn_missing_body_fat(gender: &Gender) {
+    let assumed = match gender {
+        Gender::Male => 15.0,
+        Gender::Female => 25.0,
+    };
+    println!("Warning: this strategy works from lean body mass, but no body fat is recorded.");
+    println!("An average of {:.0}% is assumed, which can make the target far off. Add your body fat for a better estimate.", assumed);
+}
+
+// Body fat percentages accepted when entered directly
+const BODY_FAT_RANGE: std::ops::RangeInclusive<f64> = 3.0..=69.0;
+
+// Tape measurements for the body fat estimate, in cm
+const CIRCUMFERENCE_RANGE: std::ops::RangeInclusive<f64> = 10.0..=300.0;
+
+// Ask for a body-fat percentage, entered directly or estimated from tape
+// measurements. Returns None if the user skips or no estimate is possible.
+pub fn prompt_body_fat(gender: &Gender, height: f64) -> Result<Option<f64>, InputError> {
+    println!("Body fat percentage:");
+    println!("1: Enter a measured value");
+    println!("2: Estimate from neck/waist{} measurements (US Navy method)",
+        if *gender == Gender::Female { "/hip" } else { "" });
+    let input = input::read_line("Enter a number (or press Enter to skip):")?;
+
+    match input.as_str() {
+        "1" => Ok(Some(input::read_number("Enter body fat (%):", BODY_FAT_RANGE)?)),
+        "2" => {
+            let read_cm = |prompt: &str| input::read_number(&format!("Enter {} circumference (in cm):", prompt), CIRCUMFERENCE_RANGE);
+            let neck = read_cm("neck")?;
+            let waist = read_cm("waist (at the navel)")?;
+            let hip = if *gender == Gender::Female { Some(read_cm("hip (at the widest point)")?) } else { None };
+
+            let estimate = us_navy_body_fat(gender, height, neck, waist, hip);
+            match estimate {
+                Some(percentage) => println!("Estimated body fat: {:.1}%", percentage),
+                None => println!("Could not estimate body fat from those measurements."),
+            }
+            Ok(estimate)
+        }
+        _ => Ok(None),
+    }
+}
+
+pub fn prompt_dietary_restrictions() -> Result<DietaryRestrictions, InputError> {
+    let names: Vec<&str> = Allergen::ALL.iter().map(|a| a.name()).collect();
+    let input = input::read_line(&format!(
+        "Enter allergens to avoid, separated by commas ({}), or press Enter for none:", names.join(", ")))?;
+
+    let (avoid_allergens, unknown) = Allergen::parse_list(&input);
+    for name in unknown {
+        println!("Unknown allergen '{}' ignored.", name);
+    }
+
+    Ok(DietaryRestrictions {
+        avoid_allergens,
+        vegan: input::read_yes_no("Vegan only?")?,
+        vegetarian: input::read_yes_no("Vegetarian only?")?,
+        halal: input::read_yes_no("Halal only?")?,
+        gluten_free: input::read_yes_no("Gluten-free only?")?,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn test_user() -> UserProfile {
+        UserProfile::new("Test".to_string(), 180.0, 80.0, 30, Gender::Male,
+            ActivityLevel::Sedentary, TargetCalorieCalcStrategy::MIFFLIN_ST_JEOR)
+    }
+
+    // Backdate the latest snapshot so the next change starts a new one
+    fn backdate_latest(user: &mut UserProfile, date: &str) {
+        user.target_history.last_mut().unwrap().effective_date = date.to_string();
+    }
+
+    #[test]
+    fn changing_macro_targets_keeps_past_days() {
+        let mut user = test_user();
+        user.macro_targets = Some(MacroTargets::PRESETS[0].1.clone());
+        user.recalculate_target();
+        backdate_latest(&mut user, "2024-01-01");
+        let before = user.macro_targets_for_date("2024-06-01");
+
+        user.macro_targets = Some(MacroTargets::PRESETS[2].1.clone());
+        user.recalculate_target();
+
+        assert_eq!(user.macro_targets_for_date("2024-06-01"), before);
+        let today = chrono::Local::now().format("%Y-%m-%d").to_string();
+        assert_ne!(user.macro_targets_for_date(&today), before);
+    }
+
+    fn day_types(training_count: usize, training_adjustment: f64) -> CalorieCycle {
+        let training_days = ["Mon", "Tue", "Wed", "Thu", "Fri", "Sat"][..training_count].iter().map(|day| day.to_string()).collect();
+        CalorieCycle::DayTypes { training_days, training_adjustment, training_macros: None, rest_macros: None }
+    }
+
+    fn mean_factor(cycle: &CalorieCycle) -> f64 {
+        WEEK.into_iter().map(|weekday| cycle.factor(weekday)).fold(0.0, |sum, factor| sum + factor) / 7.0
+    }
+
+    #[test]
+    fn day_type_factors_average_to_one() {
+        for training_count in 1..7 {
+            let cycle = day_types(training_count, 20.0);
+            assert!((mean_factor(&cycle) - 1.0).abs() < 1e-9, "{} training days", training_count);
+        }
+    }
+
+    #[test]
+    fn weekday_factors_average_to_one() {
+        let cycle = CalorieCycle::weekdays([10.0, -5.0, 10.0, -5.0, 10.0, 30.0, 30.0]);
+        assert!((mean_factor(&cycle) - 1.0).abs() < 1e-9);
+    }
+
+    #[test]
+    fn deep_rest_day_cuts_are_refused() {
+        // Six training days at +50% would leave a rest day at -200%
+        let cycle = day_types(6, 50.0);
+        assert!(cycle.lowest_factor() < 0.0);
+        assert!(check_cycle_days(&cycle, 2500.0, &Gender::Male).is_err());
+
+        let cycle = CalorieCycle::weekdays([50.0, 50.0, 50.0, 50.0, 50.0, 50.0, -50.0]);
+        assert!(check_cycle_days(&cycle, 2500.0, &Gender::Male).is_err());
+    }
+
+    #[test]
+    fn cycles_below_the_safe_minimum_are_refused() {
+        let cycle = day_types(3, 15.0);
+        assert!(check_cycle_days(&cycle, 2500.0, &Gender::Male).is_ok());
+        assert!(check_cycle_days(&cycle, 1600.0, &Gender::Male).is_err());
+    }
+
+    #[test]
+    fn lowering_the_target_turns_off_a_cycle_that_goes_too_low() {
+        let mut user = test_user();
+        user.calorie_cycle = Some(day_types(3, 15.0));
+        user.recalculate_target();
+        assert!(user.calorie_cycle.is_some());
+
+        // A fast loss goal brings the base target down to the safe minimum
+        user.set_goal(Some(WeightGoal { target_weight: 70.0, pace: crate::goal::GoalPace::WeeklyRate(1.0) }));
+        assert!(user.calorie_cycle.is_none());
+        let today = chrono::Local::now().format("%Y-%m-%d").to_string();
+        assert!(user.target_for_date(&today) >= minimum_calories(&Gender::Male));
+    }
+}
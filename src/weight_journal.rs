@@ -0,0 +1,102 @@
+use std::io;
+use chrono::NaiveDate;
+use serde::{Serialize, Deserialize};
+
+// Share of the gap between the trend and a new weigh-in that the trend moves
+// per day. Daily weights swing with water and food, so the trend moves slowly.
+const DAILY_SMOOTHING: f64 = 0.1;
+
+// A single weigh-in
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct WeightEntry {
+    pub date: String, // YYYY-MM-DD
+    pub weight: f64,  // kg
+}
+
+// Dated weigh-ins for one user, kept sorted by date with at most one per day
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(transparent)]
+pub struct WeightJournal {
+    entries: Vec<WeightEntry>,
+}
+
+impl WeightJournal {
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    // Add a weigh-in, replacing any earlier one on the same date
+    pub fn record(&mut self, date: &str, weight: f64) -> Result<(), io::Error> {
+        if NaiveDate::parse_from_str(date, "%Y-%m-%d").is_err() {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                "Invalid date format. Use YYYY-MM-DD."
+            ));
+        }
+        if weight <= 0.0 {
+            return Err(io::Error::new(io::ErrorKind::InvalidInput, "Weight must be positive"));
+        }
+
+        match self.entries.binary_search_by(|e| e.date.as_str().cmp(date)) {
+            Ok(pos) => self.entries[pos].weight = weight,
+            Err(pos) => self.entries.insert(pos, WeightEntry { date: date.to_string(), weight }),
+        }
+        Ok(())
+    }
+
+    pub fn remove(&mut self, date: &str) -> Result<(), io::Error> {
+        let pos = self.entries.iter().position(|e| e.date == date).ok_or_else(|| io::Error::new(
+            io::ErrorKind::NotFound,
+            format!("No weight recorded on {}", date)
+        ))?;
+        self.entries.remove(pos);
+        Ok(())
+    }
+
+    // Exponentially smoothed trend after each weigh-in, as (date, weight, trend).
+    // Gaps between weigh-ins are smoothed as if the trend moved each missing day.
+    pub fn trend(&self) -> Vec<(String, f64, f64)> {
+        let mut results: Vec<(String, f64, f64)> = Vec::new();
+        let mut previous: Option<(NaiveDate, f64)> = None;
+
+        for entry in &self.entries {
+            let Ok(date) = NaiveDate::parse_from_str(&entry.date, "%Y-%m-%d") else {
+                continue;
+            };
+            let trend = match previous {
+                Some((previous_date, previous_trend)) => {
+                    let days = (date - previous_date).num_days().max(1) as i32;
+                    let smoothing = 1.0 - (1.0 - DAILY_SMOOTHING).powi(days);
+                    previous_trend + smoothing * (entry.weight - previous_trend)
+                }
+                None => entry.weight,
+            };
+            previous = Some((date, trend));
+            results.push((entry.date.clone(), entry.weight, trend));
+        }
+
+        results
+    }
+
+    pub fn latest_trend(&self) -> Option<f64> {
+        self.trend().last().map(|(_, _, trend)| *trend)
+    }
+
+    // Trend weight as of a date, from the last weigh-in on or before it
+    pub fn trend_on(&self, date: &str) -> Option<f64> {
+        self.trend().into_iter()
+            .take_while(|(entry_date, _, _)| entry_date.as_str() <= date)
+            .last()
+            .map(|(_, _, trend)| trend)
+    }
+
+    // Change in trend weight over the 7 days up to the latest weigh-in.
+    // None until the journal covers at least a week.
+    pub fn weekly_change(&self) -> Option<f64> {
+        let latest = self.entries.last()?;
+        let latest_date = NaiveDate::parse_from_str(&latest.date, "%Y-%m-%d").ok()?;
+        let week_ago = (latest_date - chrono::Duration::days(7)).format("%Y-%m-%d").to_string();
+
+        Some(self.latest_trend()? - self.trend_on(&week_ago)?)
+    }
+}